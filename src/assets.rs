@@ -2,9 +2,15 @@ use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
 use bevy_kira_audio::AudioSource;
 
-use crate::{expedition::Area, AppState};
+use crate::{
+    data_read::{DataAssets, SoundClip},
+    AppState,
+};
 
-pub struct AssetLoadPlugin;
+pub struct AssetLoadPlugin {
+    /// State to move to once every asset is loaded
+    pub start: AppState,
+}
 
 impl Plugin for AssetLoadPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
@@ -14,7 +20,8 @@ impl Plugin for AssetLoadPlugin {
                 .load_collection::<SpriteAssets>()
                 .load_collection::<UiAssets>()
                 .load_collection::<SoundAssets>()
-                .continue_to_state(AppState::AreaViewer { curr_area: Area::TheCaves }),
+                .load_collection::<DataAssets>()
+                .continue_to_state(self.start.clone()),
         );
     }
 }
@@ -57,3 +64,15 @@ pub struct SoundAssets {
     #[asset(key = "rock_impact5")]
     pub mine_rock5: Handle<AudioSource>,
 }
+
+impl SoundAssets {
+    pub fn clip(&self, clip: SoundClip) -> Handle<AudioSource> {
+        match clip {
+            SoundClip::RockImpact1 => self.mine_rock1.clone(),
+            SoundClip::RockImpact2 => self.mine_rock2.clone(),
+            SoundClip::RockImpact3 => self.mine_rock3.clone(),
+            SoundClip::RockImpact4 => self.mine_rock4.clone(),
+            SoundClip::RockImpact5 => self.mine_rock5.clone(),
+        }
+    }
+}
@@ -1,30 +1,165 @@
-use bevy::prelude::*;
-use bevy_kira_audio::{Audio, AudioControl};
-use rand::{seq::SliceRandom, thread_rng};
+use std::{collections::HashMap, time::Duration};
 
-use crate::{assets::SoundAssets, mining::MineAction, AppState};
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_kira_audio::{AudioApp, AudioChannel, AudioControl, AudioInstance, AudioSource, AudioTween, PlaybackState};
+use rand::{seq::SliceRandom, thread_rng, Rng};
+
+use crate::{
+    assets::SoundAssets,
+    data_read::{SoundBank, SoundCue},
+    mining::{MineOutcome, MineResult},
+    music::DuckMusic,
+    stability::{CaveIn, StabilityLevel, StabilityWarning},
+    treasures::TreasureDiscovered,
+    AppState,
+};
+
+/// How quickly a sound is faded out when it is cut off to make room for a newer one
+const VOICE_STEAL_FADE_SECS: f32 = 0.05;
 
 pub struct AudioEventsPlugin;
 
 impl Plugin for AudioEventsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, play_mining_sound.run_if(in_state(AppState::Expedition)));
-    }
-}
-
-fn play_mining_sound(mut mine_actions: EventReader<MineAction>, sounds: Res<SoundAssets>, audio: Res<Audio>) {
-    // TODO: in the future we should play the sound based on the type of tile hit
-    let rock_sounds = vec![
-        sounds.mine_rock1.clone(),
-        sounds.mine_rock2.clone(),
-        sounds.mine_rock3.clone(),
-        sounds.mine_rock4.clone(),
-        sounds.mine_rock5.clone(),
-    ];
-    let mut rng = thread_rng();
-    for _ev in mine_actions.read() {
-        let rock_sound = rock_sounds.choose(&mut rng).unwrap();
-        audio.play(rock_sound.clone()).with_volume(1.0);
-        info!("played mining sound")
+        app.add_audio_channel::<SfxChannel>()
+            .add_audio_channel::<UiChannel>()
+            .init_resource::<Voices>()
+            .add_systems(
+                Update,
+                (play_mining_sound, play_stability_rumble, play_stingers).run_if(in_state(AppState::Expedition)),
+            )
+            .add_systems(Update, play_ui_sounds.run_if(not(in_state(AppState::AssetLoading))));
+    }
+}
+
+/// Channel for the sounds of mining and everything else happening in an expedition
+#[derive(Resource)]
+pub struct SfxChannel;
+
+/// Channel for the clicks and hovers of the menus
+#[derive(Resource)]
+pub struct UiChannel;
+
+/// Makes a button play the ui sounds when it is hovered and clicked
+#[derive(Component, Default)]
+pub struct UiSound {
+    /// Skips the hover sound when the button goes back to hovered after being pressed
+    was_pressed: bool,
+}
+
+/// Sounds still playing for each cue, used to limit how many of one cue can stack up
+#[derive(Resource, Default)]
+struct Voices(HashMap<SoundCue, Vec<Handle<AudioInstance>>>);
+
+/// Everything needed to play a cue from the sound bank
+#[derive(SystemParam)]
+struct CuePlayer<'w> {
+    bank: Res<'w, SoundBank>,
+    sounds: Res<'w, SoundAssets>,
+    sfx: Res<'w, AudioChannel<SfxChannel>>,
+    ui: Res<'w, AudioChannel<UiChannel>>,
+    voices: ResMut<'w, Voices>,
+    instances: ResMut<'w, Assets<AudioInstance>>,
+    ev_duck: EventWriter<'w, DuckMusic>,
+}
+
+impl CuePlayer<'_> {
+    /// Plays one of the cue's clips from the sound bank, `hp` shifts the pitch for cues that change with rock hp
+    fn play(&mut self, cue: SoundCue, hp: usize) {
+        let Some(def) = self.bank.get(cue) else {
+            debug!("no sound for {:?}", cue);
+            return;
+        };
+        let mut rng = thread_rng();
+        let Some(clip) = def.clips.choose(&mut rng) else {
+            return;
+        };
+        let mut pick = |(min, max): (f32, f32)| rng.gen_range(min.min(max)..=max.max(min));
+        let volume = pick(def.volume);
+        let pitch = pick(def.pitch) + def.pitch_per_hp * hp as f32;
+
+        let voices = self.voices.0.entry(cue).or_default();
+        let clip = self.sounds.clip(*clip);
+        match cue {
+            SoundCue::UiClick | SoundCue::UiHover => {
+                play_voice(&self.ui, voices, def.max_voices, &mut self.instances, clip, volume, pitch)
+            }
+            _ => play_voice(&self.sfx, voices, def.max_voices, &mut self.instances, clip, volume, pitch),
+        }
+
+        if def.duck > 0.0 {
+            self.ev_duck.send(DuckMusic(def.duck));
+        }
+    }
+}
+
+/// Plays the clip on the channel, cutting off the oldest voice first when there are already `max_voices` playing
+fn play_voice<C: Resource>(
+    channel: &AudioChannel<C>,
+    voices: &mut Vec<Handle<AudioInstance>>,
+    max_voices: usize,
+    instances: &mut Assets<AudioInstance>,
+    clip: Handle<AudioSource>,
+    volume: f32,
+    pitch: f32,
+) {
+    voices.retain(|handle| channel.state(handle) != PlaybackState::Stopped);
+    while voices.len() >= max_voices.max(1) {
+        let oldest = voices.remove(0);
+        if let Some(instance) = instances.get_mut(&oldest) {
+            instance.stop(AudioTween::linear(Duration::from_secs_f32(VOICE_STEAL_FADE_SECS)));
+        }
+    }
+    let handle = channel.play(clip).with_volume(volume as f64).with_playback_rate(pitch.max(0.05) as f64).handle();
+    voices.push(handle);
+}
+
+fn play_mining_sound(mut ev_result: EventReader<MineResult>, mut player: CuePlayer) {
+    for MineResult(outcome) in ev_result.read() {
+        let (cue, hp) = match *outcome {
+            MineOutcome::Empty => (SoundCue::Empty, 0),
+            MineOutcome::Bedrock => (SoundCue::Bedrock, 0),
+            MineOutcome::Rock { hp_left } => (SoundCue::RockHit, hp_left),
+            MineOutcome::RockBroken => (SoundCue::RockBreak, 0),
+            MineOutcome::TreasureExposed => (SoundCue::TreasureExposed, 0),
+        };
+        player.play(cue, hp);
+    }
+}
+
+fn play_stability_rumble(mut ev_warning: EventReader<StabilityWarning>, mut player: CuePlayer) {
+    for StabilityWarning(level) in ev_warning.read() {
+        let cue = match level {
+            StabilityLevel::Stable => continue,
+            StabilityLevel::Shaky => SoundCue::StabilityShaky,
+            StabilityLevel::Critical => SoundCue::StabilityCritical,
+        };
+        player.play(cue, 0);
+    }
+}
+
+/// Short musical hits for the big moments of an expedition
+fn play_stingers(
+    mut ev_discovered: EventReader<TreasureDiscovered>,
+    mut ev_cave_in: EventReader<CaveIn>,
+    mut player: CuePlayer,
+) {
+    // several treasures found by one swing only need the one stinger
+    if ev_discovered.read().count() > 0 {
+        player.play(SoundCue::TreasureFound, 0);
+    }
+    if ev_cave_in.read().count() > 0 {
+        player.play(SoundCue::CaveIn, 0);
+    }
+}
+
+fn play_ui_sounds(mut q_interaction: Query<(&Interaction, &mut UiSound), Changed<Interaction>>, mut player: CuePlayer) {
+    for (interaction, mut sound) in &mut q_interaction {
+        match *interaction {
+            Interaction::Pressed => player.play(SoundCue::UiClick, 0),
+            Interaction::Hovered if !sound.was_pressed => player.play(SoundCue::UiHover, 0),
+            _ => {}
+        }
+        sound.was_pressed = *interaction == Interaction::Pressed;
     }
 }
@@ -0,0 +1,416 @@
+//! Checks the level and treasure data files for mistakes without having to launch the game.
+//!
+//! Usage: `cargo run --bin validate_data -- [DATA_DIR]`, `DATA_DIR` defaults to `assets/data`
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    str::FromStr,
+};
+
+use miner_thing::{
+    data_read::{
+        parse_data, AreaAudio, AreaInfo, DropEntry, LevelDb, PityRule, Requirement, ShopDb, SoundBank, TreasureDb,
+        LEVELS_FILE, SHOP_FILE, SOUNDS_FILE, TREASURES_FILE, TREASURE_ATLAS_TILES,
+    },
+    expedition::Area,
+    point::{Grid, Rotation, UPoint},
+    progression::MAX_STARS,
+    run_stats::Objective,
+    stability::{LevelStability, StabilityThresholds},
+    tools::{ToolRules, ToolType, ToolUnlocks},
+    treasures::{does_treasure_fit, TreasureGrid},
+};
+
+#[derive(Default)]
+struct Report {
+    errors: Vec<String>,
+}
+
+impl Report {
+    fn error(&mut self, msg: impl Into<String>) {
+        self.errors.push(msg.into());
+    }
+}
+
+fn main() -> ExitCode {
+    let data_dir = std::env::args().nth(1).map(PathBuf::from).unwrap_or_else(|| PathBuf::from("assets/data"));
+    let mut report = Report::default();
+
+    let levels = read_data::<LevelDb>(&data_dir.join(LEVELS_FILE), &mut report);
+    let treasures = read_data::<TreasureDb>(&data_dir.join(TREASURES_FILE), &mut report);
+    let sounds = read_data::<SoundBank>(&data_dir.join(SOUNDS_FILE), &mut report);
+    let shop = read_data::<ShopDb>(&data_dir.join(SHOP_FILE), &mut report);
+
+    if let Some(treasures) = &treasures {
+        check_treasures(treasures, &mut report);
+    }
+    if let Some(levels) = &levels {
+        check_levels(levels, treasures.as_ref(), &mut report);
+    }
+    if let Some(sounds) = &sounds {
+        check_sounds(sounds, &mut report);
+    }
+    if let Some(shop) = &shop {
+        check_shop(shop, &mut report);
+    }
+
+    if report.errors.is_empty() {
+        println!("Data in {} looks good.", data_dir.display());
+        return ExitCode::SUCCESS;
+    }
+
+    eprintln!("Found {} problem(s) in {}:", report.errors.len(), data_dir.display());
+    for err in report.errors.iter() {
+        eprintln!("  - {err}");
+    }
+    ExitCode::FAILURE
+}
+
+/// Reads and parses a data file, unknown enum values like a bad stability profile are caught here
+fn read_data<A: for<'de> serde::Deserialize<'de>>(path: &Path, report: &mut Report) -> Option<A> {
+    let contents = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => {
+            report.error(format!("{}: could not be read, {e}", path.display()));
+            return None;
+        }
+    };
+    match parse_data(&contents) {
+        Ok(data) => Some(data),
+        Err(e) => {
+            report.error(format!("{}: {e}", path.display()));
+            None
+        }
+    }
+}
+
+fn check_treasures(treasures: &TreasureDb, report: &mut Report) {
+    let mut seen_ids: HashMap<u32, &str> = HashMap::new();
+    for treasure in treasures.iter() {
+        let label = format!("treasure {} ({})", treasure.id, treasure.name);
+        if let Some(other) = seen_ids.insert(treasure.id, &treasure.name) {
+            report.error(format!("{label}: id is already used by {other}"));
+        }
+
+        if treasure.shape.len() != treasure.width * treasure.height {
+            report.error(format!(
+                "{label}: shape has {} cells but width x height is {}x{}",
+                treasure.shape.len(),
+                treasure.width,
+                treasure.height
+            ));
+        }
+        if treasure.shape.iter().any(|tile| *tile < -1) {
+            report.error(format!("{label}: shape has an atlas index below -1"));
+        }
+        if treasure.shape.iter().any(|tile| *tile >= TREASURE_ATLAS_TILES) {
+            report.error(format!("{label}: shape has an atlas index past the {TREASURE_ATLAS_TILES} atlas tiles"));
+        }
+        if treasure.shape.iter().all(|tile| *tile == -1) {
+            report.error(format!("{label}: shape is completely empty"));
+        }
+
+        for (idx, orientation) in treasure.orientations.iter().enumerate() {
+            let o_label =
+                format!("{label} orientation {idx} ({:?}, mirrored: {})", orientation.rotation, orientation.mirrored);
+            if let Some(atlas) = &orientation.atlas {
+                if atlas.len() != treasure.shape.len() {
+                    report.error(format!(
+                        "{o_label}: atlas has {} cells but shape has {}",
+                        atlas.len(),
+                        treasure.shape.len()
+                    ));
+                }
+                if atlas.iter().any(|tile| !(-1..TREASURE_ATLAS_TILES).contains(tile)) {
+                    report.error(format!(
+                        "{o_label}: atlas has an index outside of the {TREASURE_ATLAS_TILES} atlas tiles"
+                    ));
+                }
+            }
+            if orientation.rotation == Rotation::None && !orientation.mirrored {
+                report.error(format!("{o_label}: is the same as the authored shape"));
+            }
+            if treasure.orientations[..idx]
+                .iter()
+                .any(|other| other.rotation == orientation.rotation && other.mirrored == orientation.mirrored)
+            {
+                report.error(format!("{o_label}: is listed more than once"));
+            }
+        }
+    }
+}
+
+fn check_shop(shop: &ShopDb, report: &mut Report) {
+    for (idx, prices) in shop.upgrades.iter().enumerate() {
+        let label = format!("upgrade {:?}", prices.kind);
+        if shop.upgrades[..idx].iter().any(|other| other.kind == prices.kind) {
+            report.error(format!("{label}: is priced more than once"));
+        }
+        if prices.costs.is_empty() {
+            report.error(format!("{label}: has no levels to buy"));
+        }
+    }
+    for (idx, unlock) in shop.unlocks.iter().enumerate() {
+        let label = format!("unlock {:?}", unlock.rotation);
+        if shop.unlocks[..idx].iter().any(|other| other.rotation == unlock.rotation) {
+            report.error(format!("{label}: is priced more than once"));
+        }
+        if ToolUnlocks::default().is_unlocked(&ToolType::Pickaxe { rotation: unlock.rotation }) {
+            report.error(format!("{label}: starts unlocked so it can never be bought"));
+        }
+    }
+}
+
+fn check_sounds(sounds: &SoundBank, report: &mut Report) {
+    for (idx, def) in sounds.iter().enumerate() {
+        let label = format!("sound {:?}", def.cue);
+        if sounds[..idx].iter().any(|other| other.cue == def.cue) {
+            report.error(format!("{label}: is listed more than once"));
+        }
+        if def.clips.is_empty() {
+            report.error(format!("{label}: has no clips to play"));
+        }
+        for (name, (min, max)) in [("volume", def.volume), ("pitch", def.pitch)] {
+            if min > max {
+                report.error(format!("{label}: {name} range starts at {min} which is above its end {max}"));
+            }
+        }
+        if def.volume.0 < 0.0 {
+            report.error(format!("{label}: volume can't be negative"));
+        }
+        if def.pitch.0 <= 0.0 {
+            report.error(format!("{label}: pitch must be above 0"));
+        }
+        if def.max_voices == 0 {
+            report.error(format!("{label}: max_voices must be at least 1"));
+        }
+        if !(0.0..=1.0).contains(&def.duck) {
+            report.error(format!("{label}: duck {} must be between 0 and 1", def.duck));
+        }
+    }
+}
+
+fn check_area_audio(area_name: &str, audio: &AreaAudio, report: &mut Report) {
+    for (idx, layer) in audio.music.iter().enumerate() {
+        let label = format!("area \"{area_name}\" music layer {idx}");
+        if layer.path.trim().is_empty() {
+            report.error(format!("{label}: has no path to a track"));
+        }
+        if !(0.0..=1.0).contains(&layer.intensity) {
+            report.error(format!("{label}: intensity {} must be between 0 and 1", layer.intensity));
+        }
+        if layer.volume < 0.0 {
+            report.error(format!("{label}: volume can't be negative"));
+        }
+    }
+    for (idx, sound) in audio.ambience.iter().enumerate() {
+        let label = format!("area \"{area_name}\" ambient sound {idx}");
+        if sound.clips.is_empty() {
+            report.error(format!("{label}: has no clips to play"));
+        }
+        for (name, (min, max)) in [("every", sound.every), ("volume", sound.volume), ("pitch", sound.pitch)] {
+            if min > max {
+                report.error(format!("{label}: {name} range starts at {min} which is above its end {max}"));
+            }
+        }
+        if sound.every.0 <= 0.0 {
+            report.error(format!("{label}: must wait more than 0 seconds between plays"));
+        }
+        if sound.volume.0 < 0.0 {
+            report.error(format!("{label}: volume can't be negative"));
+        }
+        if sound.pitch.0 <= 0.0 {
+            report.error(format!("{label}: pitch must be above 0"));
+        }
+        if !(0.0..=1.0).contains(&sound.intensity) {
+            report.error(format!("{label}: intensity {} must be between 0 and 1", sound.intensity));
+        }
+        if !(0.0..=1.0).contains(&sound.busier) {
+            report.error(format!("{label}: busier {} must be between 0 and 1", sound.busier));
+        }
+    }
+}
+
+fn check_levels(levels: &LevelDb, treasures: Option<&TreasureDb>, report: &mut Report) {
+    for (area_name, area) in levels.iter() {
+        if Area::from_str(area_name).is_err() {
+            report.error(format!("area \"{area_name}\": is not an area the game knows about"));
+        }
+        if area.levels.is_empty() {
+            report.error(format!("area \"{area_name}\": has no levels"));
+        }
+        if let (Some(table), Some(treasures)) = (&area.drop_table, treasures) {
+            check_drop_table(&format!("area \"{area_name}\""), table, treasures, report);
+        }
+        if let Some(pity) = &area.pity {
+            check_pity(area_name, area, pity, treasures, report);
+        }
+        check_area_audio(area_name, &area.audio, report);
+        let is_first_area = Area::from_str(area_name).is_ok_and(|a| Area::ALL.first() == Some(&a));
+        let area_label = format!("area \"{area_name}\"");
+        check_requirements(&area_label, &area.requires, is_first_area, levels, treasures, report);
+
+        for (idx, level) in area.levels.iter().enumerate() {
+            let label = format!("{area_name} level {idx} ({})", level.name);
+            check_requirements(&label, &level.requires, idx == 0, levels, treasures, report);
+            check_objectives(&label, &level.objectives, level.stability, report);
+            check_tool_rules(&label, &level.tools, &level.objectives, report);
+            if let Some(thresholds) = &level.thresholds {
+                check_thresholds(&label, thresholds, report);
+            }
+            if level.size.0 == 0 || level.size.1 == 0 {
+                report.error(format!("{label}: size {:?} has no room to mine", level.size));
+                continue;
+            }
+
+            let Some(treasures) = treasures else {
+                continue;
+            };
+            if let Some(table) = &level.drop_table {
+                check_drop_table(&label, table, treasures, report);
+            }
+            for treasure in treasures.iter() {
+                if treasure.placeable_shapes().iter().any(|shape| !fits_anywhere(level.size, &shape.grid)) {
+                    report.error(format!(
+                        "{label}: treasure {} ({}) can never fit in at least one orientation",
+                        treasure.id, treasure.name
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// `is_first` is for when there is no level before this one so it can not ask for the previous level to be cleared
+fn check_requirements(
+    label: &str,
+    requirements: &[Requirement],
+    is_first: bool,
+    levels: &LevelDb,
+    treasures: Option<&TreasureDb>,
+    report: &mut Report,
+) {
+    for req in requirements.iter() {
+        match req {
+            Requirement::ClearPrevious if is_first => {
+                report.error(format!("{label}: requires clearing the previous level but there is none before it"));
+            }
+            Requirement::ClearLevel { area, level } => {
+                let exists = levels.get(area).is_some_and(|a| a.levels.iter().any(|l| &l.name == level));
+                if !exists {
+                    report.error(format!("{label}: requires clearing {level} in {area} which does not exist"));
+                }
+            }
+            Requirement::Treasure(id) => {
+                if treasures.is_some_and(|treasures| treasures.get_by_id(*id).is_none()) {
+                    report.error(format!("{label}: requires treasure {id} which does not exist"));
+                }
+            }
+            Requirement::Stars(stars) => {
+                let most_stars = levels.values().map(|area| area.levels.len() as u32).sum::<u32>() * MAX_STARS;
+                if *stars > most_stars {
+                    report.error(format!("{label}: requires {stars} stars but only {most_stars} can be earned"));
+                }
+            }
+            Requirement::ClearPrevious | Requirement::Tool(_) => {}
+        }
+    }
+}
+
+fn check_objectives(label: &str, objectives: &[Objective], stability: LevelStability, report: &mut Report) {
+    for objective in objectives.iter() {
+        match objective {
+            Objective::MaxStabilitySpent(max) if *max as i32 >= stability.start() => {
+                report.error(format!("{label}: objective {objective:?} is met by any clear"));
+            }
+            Objective::MaxSwings(0) => {
+                report.error(format!("{label}: objective {objective:?} can never be met"));
+            }
+            Objective::TimeLimit(secs) if !secs.is_finite() || *secs <= 0.0 => {
+                report.error(format!("{label}: objective {objective:?} needs a time above 0"));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn check_thresholds(label: &str, thresholds: &StabilityThresholds, report: &mut Report) {
+    let StabilityThresholds { shaky, critical } = *thresholds;
+    if !(0.0..=1.0).contains(&shaky) || !(0.0..=1.0).contains(&critical) {
+        report.error(format!("{label}: stability thresholds {thresholds:?} need to be between 0 and 1"));
+    } else if critical >= shaky {
+        report.error(format!("{label}: critical stability threshold {critical} is not below the shaky one {shaky}"));
+    }
+}
+
+fn check_tool_rules(label: &str, rules: &ToolRules, objectives: &[Objective], report: &mut Report) {
+    if rules.loadout_size == Some(0) {
+        report.error(format!("{label}: a loadout size of 0 leaves no tools to mine with"));
+    }
+    let Some(allowed) = &rules.allowed else {
+        return;
+    };
+    if allowed.is_empty() && rules.required.is_empty() {
+        report.error(format!("{label}: allows no tools"));
+    }
+    for tool in rules.required.iter().filter(|tool| !allowed.contains(tool)) {
+        report.error(format!("{label}: requires {tool:?} but does not allow it"));
+    }
+    for objective in objectives.iter() {
+        if let Objective::OnlyTool(tool) = objective {
+            if !allowed.contains(tool) && !rules.required.contains(tool) {
+                report.error(format!("{label}: objective {objective:?} asks for a tool that can't be brought"));
+            }
+        }
+    }
+}
+
+fn check_drop_table(label: &str, table: &[DropEntry], treasures: &TreasureDb, report: &mut Report) {
+    if table.is_empty() {
+        report.error(format!("{label}: drop table is empty"));
+    }
+    for entry in table.iter() {
+        if treasures.get_by_id(entry.treasure).is_none() {
+            report.error(format!("{label}: drop table has treasure {} which does not exist", entry.treasure));
+        }
+        if entry.weight == 0 {
+            report.error(format!("{label}: drop table has treasure {} with a weight of 0", entry.treasure));
+        }
+    }
+}
+
+/// The pity rarity has to be something the area can actually drop or the guarantee never ends
+fn check_pity(area_name: &str, area: &AreaInfo, pity: &PityRule, treasures: Option<&TreasureDb>, report: &mut Report) {
+    if pity.within == 0 {
+        report.error(format!("area \"{area_name}\": pity needs to be within at least 1 clear"));
+    }
+    let Some(treasures) = treasures else {
+        return;
+    };
+    if !treasures.iter().any(|t| t.rarity >= pity.rarity) {
+        report.error(format!("area \"{area_name}\": pity wants {:?} but no treasure is that rare", pity.rarity));
+        return;
+    }
+    for (idx, level) in area.levels.iter().enumerate() {
+        let Some(table) = level.drop_table.as_ref().or(area.drop_table.as_ref()) else {
+            continue;
+        };
+        let has_pity_rarity =
+            table.iter().filter_map(|entry| treasures.get_by_id(entry.treasure)).any(|t| t.rarity >= pity.rarity);
+        if !has_pity_rarity {
+            report.error(format!(
+                "{area_name} level {idx} ({}): drop table has nothing for the {:?} pity",
+                level.name, pity.rarity
+            ));
+        }
+    }
+}
+
+/// Tries every position of an empty level for the treasure
+fn fits_anywhere(size: (usize, usize), shape: &Grid<i32>) -> bool {
+    let grid = TreasureGrid::new(size.0, size.1);
+    (0..size.1).any(|y| (0..size.0).any(|x| does_treasure_fit(&grid, shape, UPoint::new(x, y))))
+}
@@ -1,16 +1,43 @@
-use bevy::{core_pipeline::bloom::BloomSettings, prelude::*, render::camera::ScalingMode};
+use bevy::{
+    core_pipeline::bloom::BloomSettings,
+    ecs::system::SystemParam,
+    input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
+    prelude::*,
+    render::camera::ScalingMode,
+    window::{PrimaryWindow, WindowResized},
+};
 
-use crate::{AppState, SPRITE_PX_X, SPRITE_PX_Y};
+use crate::{
+    effects::ShakeOffset,
+    point::{tile_to_world, IPoint},
+    settings::{KeyAction, Settings},
+    ui::REFERENCE_RESOLUTION,
+    AppState, SPRITE_PX_X, SPRITE_PX_Y,
+};
 
 pub struct CameraPlugin;
 
 pub const CAMERA_Z: f32 = 100.0;
+/// Window pixels per world pixel outside of an expedition
+const DEFAULT_SCALE: f32 = 2.0;
+/// How far in the player can zoom, relative to the scale that fits the whole level
+const MAX_ZOOM: f32 = 4.0;
+/// Window pixels per second the camera moves when panning with the keyboard
+const PAN_SPEED: f32 = 500.0;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<CameraUpdate>()
+            .init_resource::<CameraBounds>()
             .add_systems(Startup, init_camera)
-            .add_systems(OnEnter(AppState::Expedition), update_camera_for_expedition);
+            .add_systems(OnEnter(AppState::Expedition), update_camera_for_expedition)
+            .add_systems(
+                Update,
+                (refit_camera.run_if(on_event::<WindowResized>()), zoom_camera, pan_camera)
+                    .chain()
+                    .run_if(in_state(AppState::Expedition)),
+            )
+            .add_systems(OnExit(AppState::Expedition), reset_camera);
     }
 }
 
@@ -21,36 +48,226 @@ pub struct MainCamera;
 pub struct CameraUpdate {
     pub width: f32,
     pub height: f32,
+    /// Preferred window pixels per world pixel, lowered when the level would not fit in the window
     pub scale: f32,
 }
 
+/// Area of the world the camera is kept inside of during an expedition
+#[derive(Resource, Default)]
+struct CameraBounds {
+    min: Vec2,
+    max: Vec2,
+    /// Scale where the whole of the bounds fit in the window
+    fit_scale: f32,
+}
+
+impl CameraBounds {
+    fn new(min: Vec2, max: Vec2, window: Vec2) -> Self {
+        let mut bounds = Self { min, max, fit_scale: 1.0 };
+        bounds.fit_to(window);
+        bounds
+    }
+
+    /// Finds the largest scale that still shows all of the bounds, whole numbers are used when the window is big
+    /// enough so every world pixel is drawn with the same number of window pixels
+    fn fit_to(&mut self, window: Vec2) {
+        let fit = (window / (self.max - self.min)).min_element();
+        self.fit_scale = if fit >= 1.0 { fit.floor() } else { fit };
+    }
+
+    fn scale_range(&self) -> (f32, f32) {
+        (self.fit_scale, self.fit_scale * MAX_ZOOM)
+    }
+
+    /// Keeps the view inside of the bounds, centering on any axis the view is larger than
+    fn clamp(&self, center: Vec2, view_size: Vec2) -> Vec2 {
+        let half = view_size / 2.0;
+        let clamp_axis = |c: f32, min: f32, max: f32, half: f32| {
+            if max - min <= half * 2.0 {
+                (min + max) / 2.0
+            } else {
+                c.clamp(min + half, max - half)
+            }
+        };
+        Vec2::new(
+            clamp_axis(center.x, self.min.x, self.max.x, half.x),
+            clamp_axis(center.y, self.min.y, self.max.y, half.y),
+        )
+    }
+}
+
 fn init_camera(mut commands: Commands) {
     commands.spawn((
         Camera2dBundle {
             transform: Transform::from_xyz(0.0, 0.0, CAMERA_Z),
-            projection: OrthographicProjection { scaling_mode: ScalingMode::WindowSize(2.0), ..default() },
+            projection: OrthographicProjection { scaling_mode: ScalingMode::WindowSize(DEFAULT_SCALE), ..default() },
             camera: Camera { hdr: true, ..default() },
             // tonemapping: Tonemapping::AgX,
             ..default()
         },
         MainCamera,
+        ShakeOffset::default(),
         BloomSettings::NATURAL,
     ));
     debug!("debugging in the camera");
 }
 
+fn window_size(q_window: &Query<&Window, With<PrimaryWindow>>) -> Vec2 {
+    q_window.get_single().map_or(REFERENCE_RESOLUTION, |window| Vec2::new(window.width(), window.height()))
+}
+
+fn current_scale(projection: &OrthographicProjection) -> f32 {
+    match projection.scaling_mode {
+        ScalingMode::WindowSize(scale) => scale,
+        _ => DEFAULT_SCALE,
+    }
+}
+
 fn update_camera_for_expedition(
-    mut q_camera: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+    mut q_camera: Query<(&mut Transform, &mut OrthographicProjection, &mut ShakeOffset), With<MainCamera>>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
     mut ev_cam_move: EventReader<CameraUpdate>,
+    mut bounds: ResMut<CameraBounds>,
 ) {
     let mut cam = q_camera.single_mut();
     let Some(ev) = ev_cam_move.read().next() else {
         return;
     };
-    *cam.0 = Transform::from_xyz(
-        ev.width as f32 * SPRITE_PX_X as f32 / 2.0,
-        ev.height as f32 * SPRITE_PX_Y as f32 / 2.0,
-        CAMERA_Z,
-    );
-    // cam.1.scaling_mode = ScalingMode::WindowSize(ev.scale);
+    let (width, height) = (ev.width as i32, ev.height as i32);
+    let half_tile = Vec2::new(SPRITE_PX_X as f32, SPRITE_PX_Y as f32) / 2.0;
+    // the border around the grid should stay in view, with some room under it for the toolbar
+    let min = tile_to_world(IPoint::new(-1, -4)) - half_tile;
+    let max = tile_to_world(IPoint::new(width, height)) + half_tile;
+    *bounds = CameraBounds::new(min, max, window_size(&q_window));
+
+    let (min_scale, max_scale) = bounds.scale_range();
+    let scale = ev.scale.min(bounds.fit_scale).clamp(min_scale, max_scale);
+    cam.1.scaling_mode = ScalingMode::WindowSize(scale);
+    *cam.0 = Transform::from_translation(snap_to_pixel((min + max) / 2.0, scale).extend(CAMERA_Z));
+    cam.2 .0 = Vec2::ZERO;
+}
+
+/// Moves the camera to the closest spot that lines world pixels up with window pixels
+fn snap_to_pixel(pos: Vec2, scale: f32) -> Vec2 {
+    (pos * scale).round() / scale
+}
+
+/// Fits the level to the new window size, keeping how far the player had zoomed in
+fn refit_camera(
+    mut q_camera: Query<&mut OrthographicProjection, With<MainCamera>>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    mut bounds: ResMut<CameraBounds>,
+) {
+    let Ok(mut projection) = q_camera.get_single_mut() else {
+        return;
+    };
+    let zoom = current_scale(&projection) / bounds.fit_scale;
+    bounds.fit_to(window_size(&q_window));
+    let (min_scale, max_scale) = bounds.scale_range();
+    let scale = bounds.fit_scale * zoom;
+    let scale = if scale >= 1.0 { scale.round() } else { scale };
+    projection.scaling_mode = ScalingMode::WindowSize(scale.clamp(min_scale, max_scale));
+}
+
+/// Mouse wheel or the zoom keys zoom a whole step at a time, the fit key goes back to fitting the whole level
+fn zoom_camera(
+    mut q_camera: Query<&mut OrthographicProjection, With<MainCamera>>,
+    mut ev_scroll: EventReader<MouseWheel>,
+    keeb: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    bounds: Res<CameraBounds>,
+) {
+    let mut steps: f32 = ev_scroll
+        .read()
+        .map(|ev| match ev.unit {
+            MouseScrollUnit::Line => ev.y,
+            MouseScrollUnit::Pixel => ev.y / 50.0,
+        })
+        .sum();
+    if settings.keybinds.just_pressed(KeyAction::ZoomIn, &keeb) {
+        steps += 1.0;
+    }
+    if settings.keybinds.just_pressed(KeyAction::ZoomOut, &keeb) {
+        steps -= 1.0;
+    }
+    let Ok(mut projection) = q_camera.get_single_mut() else {
+        return;
+    };
+    let (min_scale, max_scale) = bounds.scale_range();
+    if settings.keybinds.just_pressed(KeyAction::ZoomReset, &keeb) {
+        projection.scaling_mode = ScalingMode::WindowSize(min_scale);
+        return;
+    }
+    if steps == 0.0 {
+        return;
+    }
+    let current = current_scale(&projection);
+    let scale = if steps > 0.0 { (current + 1.0).floor() } else { (current - 1.0).ceil() };
+    let scale = scale.clamp(min_scale, max_scale);
+    projection.scaling_mode = ScalingMode::WindowSize(scale);
+}
+
+/// Everything the player can pan the camera with
+#[derive(SystemParam)]
+struct PanInput<'w, 's> {
+    ev_motion: EventReader<'w, 's, MouseMotion>,
+    keeb: Res<'w, Input<KeyCode>>,
+    mouse: Res<'w, Input<MouseButton>>,
+    settings: Res<'w, Settings>,
+    time: Res<'w, Time>,
+}
+
+impl PanInput<'_, '_> {
+    /// How far the player wants to move the camera this frame, in window pixels
+    fn pan(&mut self) -> Vec2 {
+        let mut pan = Vec2::ZERO;
+        for (action, dir) in [
+            (KeyAction::PanLeft, Vec2::NEG_X),
+            (KeyAction::PanRight, Vec2::X),
+            (KeyAction::PanUp, Vec2::Y),
+            (KeyAction::PanDown, Vec2::NEG_Y),
+        ] {
+            if self.settings.keybinds.pressed(action, &self.keeb) {
+                pan += dir * PAN_SPEED * self.time.delta_seconds();
+            }
+        }
+        let dragged: Vec2 = self.ev_motion.read().map(|ev| ev.delta).sum();
+        if self.mouse.pressed(MouseButton::Middle) {
+            // dragging moves the level with the cursor, and window y points down
+            pan += Vec2::new(-dragged.x, dragged.y);
+        }
+        pan
+    }
+}
+
+/// The pan keys move the camera, as does dragging with the middle mouse button.
+/// Also keeps the camera inside of the level after a zoom
+fn pan_camera(
+    mut q_camera: Query<(&mut Transform, &OrthographicProjection, &ShakeOffset), With<MainCamera>>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    mut input: PanInput,
+    bounds: Res<CameraBounds>,
+) {
+    let pan = input.pan();
+
+    let Ok((mut transform, projection, offset)) = q_camera.get_single_mut() else {
+        return;
+    };
+    let scale = current_scale(projection);
+    let base = transform.translation.truncate() - offset.0;
+    let center = snap_to_pixel(bounds.clamp(base + pan / scale, window_size(&q_window) / scale), scale);
+    if center != base {
+        transform.translation = (center + offset.0).extend(CAMERA_Z);
+    }
+}
+
+fn reset_camera(
+    mut q_camera: Query<(&mut Transform, &mut OrthographicProjection, &mut ShakeOffset), With<MainCamera>>,
+) {
+    let Ok((mut transform, mut projection, mut offset)) = q_camera.get_single_mut() else {
+        return;
+    };
+    *transform = Transform::from_xyz(0.0, 0.0, CAMERA_Z);
+    offset.0 = Vec2::ZERO;
+    projection.scaling_mode = ScalingMode::WindowSize(DEFAULT_SCALE);
 }
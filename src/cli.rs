@@ -0,0 +1,131 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use clap::{Parser, ValueEnum};
+
+use crate::{
+    expedition::{ActiveLevel, Area, LevelChange},
+    settings::WindowModeSetting,
+    tools::{ActiveTool, PickaxeRotation, ToolType},
+    AppState,
+};
+
+/// Command line options, mostly used by testers and designers to jump straight into an expedition
+#[derive(Parser, Resource, Debug, Clone)]
+#[command(name = "Underground Miner", about = "Puzzle game about mining special items in order to progress.")]
+pub struct LaunchArgs {
+    /// Area to open, e.g. "The Caves" or "the-caves"
+    #[arg(long)]
+    pub area: Option<Area>,
+    /// Index of the level within the area to start an expedition in, skips the area viewer.
+    /// The level's unlock requirements are ignored so any level can be tested
+    #[arg(long)]
+    pub level: Option<usize>,
+    /// Seed used to generate the expedition
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Tool to hold when the game starts, it is always brought into the launched level even if not unlocked
+    #[arg(long, value_enum)]
+    pub tool: Option<StartTool>,
+    /// Overrides the starting stability of the launched expedition
+    #[arg(long)]
+    pub stability: Option<i32>,
+    /// Run in a window, overrides the settings file
+    #[arg(long, conflicts_with = "fullscreen")]
+    pub windowed: bool,
+    /// Run borderless fullscreen, overrides the settings file
+    #[arg(long)]
+    pub fullscreen: bool,
+    /// Window resolution as WIDTHxHEIGHT, overrides the settings file
+    #[arg(long, value_parser = parse_resolution)]
+    pub resolution: Option<(f32, f32)>,
+    /// Turns off screen shake and falling debris, overrides the settings file
+    #[arg(long)]
+    pub reduced_motion: bool,
+    /// Directory holding the level and treasure data files, relative to the game folder
+    #[arg(long, default_value = "assets/data")]
+    pub data_dir: PathBuf,
+}
+
+#[derive(ValueEnum, Debug, Copy, Clone)]
+pub enum StartTool {
+    Hammer,
+    PickaxeHorizontal,
+    PickaxeVertical,
+    PickaxeCross,
+}
+
+impl From<StartTool> for ToolType {
+    fn from(value: StartTool) -> Self {
+        match value {
+            StartTool::Hammer => ToolType::TinyHammer,
+            StartTool::PickaxeHorizontal => ToolType::Pickaxe { rotation: PickaxeRotation::Horizontal },
+            StartTool::PickaxeVertical => ToolType::Pickaxe { rotation: PickaxeRotation::Vertical },
+            StartTool::PickaxeCross => ToolType::Pickaxe { rotation: PickaxeRotation::Cross },
+        }
+    }
+}
+
+impl LaunchArgs {
+    /// The window mode asked for with `--windowed` or `--fullscreen`, if either was given
+    pub fn window_mode(&self) -> Option<WindowModeSetting> {
+        if self.fullscreen {
+            Some(WindowModeSetting::Borderless)
+        } else if self.windowed {
+            Some(WindowModeSetting::Windowed)
+        } else {
+            None
+        }
+    }
+
+    /// The `--tool` to bring into `level` when it is the level launched with `--level`, so the level's tool rules
+    /// can't take it away
+    pub fn launched_tool(&self, level: &ActiveLevel) -> Option<ToolType> {
+        let launched =
+            self.level == Some(level.level_idx) && self.area.as_ref().unwrap_or(&Area::TheCaves) == &level.area;
+        self.tool.filter(|_| launched).map(ToolType::from)
+    }
+
+    /// The area the game lands on once loading is done
+    pub fn start_state(&self) -> AppState {
+        AppState::AreaViewer { curr_area: self.area.clone().unwrap_or(Area::TheCaves) }
+    }
+}
+
+fn parse_resolution(s: &str) -> Result<(f32, f32), String> {
+    let (w, h) = s.split_once('x').ok_or_else(|| format!("expected WIDTHxHEIGHT, got {s}"))?;
+    let w = w.parse::<u32>().map_err(|e| format!("bad width {w}: {e}"))?;
+    let h = h.parse::<u32>().map_err(|e| format!("bad height {h}: {e}"))?;
+    Ok((w as f32, h as f32))
+}
+
+pub struct LaunchPlugin(pub LaunchArgs);
+
+impl Plugin for LaunchPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.0.clone()).add_systems(OnExit(AppState::AssetLoading), launch_from_args);
+    }
+}
+
+/// Applies the launch args once everything has loaded, sending a level change if a level was requested
+fn launch_from_args(
+    args: Res<LaunchArgs>,
+    mut active_tool: ResMut<ActiveTool>,
+    mut ev_level_change: EventWriter<LevelChange>,
+) {
+    if let Some(tool) = args.tool {
+        active_tool.0 = tool.into();
+    }
+
+    let Some(level_idx) = args.level else {
+        return;
+    };
+    info!("launching straight into level {} with {:?}", level_idx, args);
+    ev_level_change.send(LevelChange {
+        area: args.area.clone().unwrap_or(Area::TheCaves),
+        level_idx,
+        seed: args.seed,
+        stability: args.stability,
+        ignore_requirements: true,
+    });
+}
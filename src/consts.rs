@@ -1,5 +1,3 @@
-#[allow(unused_variables)]
-
 // -=-=-=-=-=-=-= Texture Atlas Indices =-=-=-=-=-=-=-=-
 
 // Expedition Levels
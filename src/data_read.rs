@@ -1,47 +1,517 @@
-use std::{collections::HashMap, fs, sync::OnceLock};
+use std::{collections::HashMap, fmt::Display, marker::PhantomData};
 
-use json5;
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use bevy_asset_loader::prelude::*;
 use serde::Deserialize;
 
-use crate::stability::LevelStability;
+use crate::{
+    point::{Grid, Rotation},
+    run_stats::Objective,
+    stability::{LevelStability, StabilityThresholds},
+    tools::{PickaxeRotation, ToolRules, ToolType, UpgradeKind},
+};
 
-pub static LEVEL_DB: OnceLock<HashMap<String, AreaInfo>> = OnceLock::new();
-pub static TREASURE_DB: OnceLock<Vec<TreasureInfo>> = OnceLock::new();
+pub struct DataPlugin;
 
-#[derive(Deserialize)]
+impl Plugin for DataPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<LevelDb>()
+            .init_asset::<TreasureDb>()
+            .init_asset::<SoundBank>()
+            .init_asset::<ShopDb>()
+            .register_asset_loader(Json5Loader::<LevelDb>::new(&["levels.json5"]))
+            .register_asset_loader(Json5Loader::<TreasureDb>::new(&["treasures.json5"]))
+            .register_asset_loader(Json5Loader::<SoundBank>::new(&["sounds.json5"]))
+            .register_asset_loader(Json5Loader::<ShopDb>::new(&["shop.json5"]))
+            .init_resource::<LevelDb>()
+            .init_resource::<TreasureDb>()
+            .init_resource::<SoundBank>()
+            .init_resource::<ShopDb>()
+            // PreUpdate so a freshly loaded db is in place before any state transition reads it
+            .add_systems(
+                PreUpdate,
+                (
+                    sync_data_asset::<LevelDb>,
+                    sync_data_asset::<TreasureDb>,
+                    sync_data_asset::<SoundBank>,
+                    sync_data_asset::<ShopDb>,
+                ),
+            );
+    }
+}
+
+/// Names of the data files inside the data directory, these must match the paths in [`DataAssets`]
+pub const LEVELS_FILE: &str = "world.levels.json5";
+pub const TREASURES_FILE: &str = "world.treasures.json5";
+pub const SOUNDS_FILE: &str = "world.sounds.json5";
+pub const SHOP_FILE: &str = "world.shop.json5";
+
+/// Handles to the game data files, kept alive so the file watcher can hot reload them
+#[derive(AssetCollection, Resource)]
+pub struct DataAssets {
+    #[asset(path = "data://world.levels.json5")]
+    pub levels: Handle<LevelDb>,
+    #[asset(path = "data://world.treasures.json5")]
+    pub treasures: Handle<TreasureDb>,
+    #[asset(path = "data://world.sounds.json5")]
+    pub sounds: Handle<SoundBank>,
+    #[asset(path = "data://world.shop.json5")]
+    pub shop: Handle<ShopDb>,
+}
+
+/// Every area in the game keyed by the area's display name
+#[derive(Asset, Resource, TypePath, Deserialize, Default, Clone, Deref)]
+#[serde(transparent)]
+pub struct LevelDb(HashMap<String, AreaInfo>);
+
+/// Every treasure that can be placed in an expedition
+#[derive(Asset, Resource, TypePath, Deserialize, Default, Clone, Deref)]
+#[serde(transparent)]
+pub struct TreasureDb(Vec<TreasureInfo>);
+
+#[derive(Deserialize, Clone)]
 pub struct TreasureInfo {
     pub id: u32,
     pub name: String,
+    #[serde(default)]
+    pub description: String,
     // a 1d array representing the shape and atlas indices of the treasure
     // -1 represents an empty cell
     pub shape: Vec<i32>,
     pub width: usize,
     pub height: usize,
+    #[serde(default)]
+    pub rarity: Rarity,
+    /// Coins the treasure sells for at the shop
+    #[serde(default)]
+    pub value: u32,
+    // other ways the treasure can be placed, the authored shape is always allowed
+    #[serde(default)]
+    pub orientations: Vec<TreasureOrientation>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Legendary,
+}
+
+impl Rarity {
+    /// Weight used for the treasure when a level does not have a drop table
+    pub fn default_weight(&self) -> u32 {
+        match self {
+            Rarity::Common => 60,
+            Rarity::Uncommon => 25,
+            Rarity::Rare => 10,
+            Rarity::Legendary => 5,
+        }
+    }
+}
+
+/// Number of tiles in the treasures texture atlas, every atlas index in the treasure data has to be below this
+pub const TREASURE_ATLAS_TILES: i32 = 11;
+
+/// A rotated and/or mirrored version of a treasure, optionally with its own sprites
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TreasureOrientation {
+    #[serde(default)]
+    pub rotation: Rotation,
+    #[serde(default)]
+    pub mirrored: bool,
+    // atlas index to use for each cell of `shape` once transformed, in the same order as `shape`.
+    // Without it the authored sprites are reused and turned to match
+    #[serde(default)]
+    pub atlas: Option<Vec<i32>>,
+}
+
+/// A shape a treasure can be placed as along with how its sprites have to be drawn
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlaceableShape {
+    pub grid: Grid<i32>,
+    /// Turn applied to every sprite, after mirroring it
+    pub sprite_rotation: Rotation,
+    pub sprite_mirrored: bool,
+}
+
+impl TreasureInfo {
+    /// The shape as a grid, `None` if the shape does not match its width and height.
+    /// Shapes are authored with the top row first so they are flipped to have row 0 at the bottom
+    pub fn shape_grid(&self) -> Option<Grid<i32>> {
+        Grid::from_vec(self.width, self.height, self.shape.clone()).map(|grid| grid.flipped_y())
+    }
+
+    /// Mirrors then rotates the shape, swapping in the orientation's sprites for each cell when it has them
+    /// and turning the authored sprites otherwise.
+    /// `None` if the shape or the orientation's atlas does not match the width and height
+    pub fn transformed_shape(&self, orientation: &TreasureOrientation) -> Option<PlaceableShape> {
+        let (cells, sprite_rotation, sprite_mirrored) = match &orientation.atlas {
+            Some(atlas) if atlas.len() != self.shape.len() => return None,
+            Some(atlas) => (
+                self.shape
+                    .iter()
+                    .zip(atlas.iter())
+                    .map(|(tile, atlas_idx)| if *tile == -1 { -1 } else { *atlas_idx })
+                    .collect(),
+                Rotation::None,
+                false,
+            ),
+            None => (self.shape.clone(), orientation.rotation, orientation.mirrored),
+        };
+        let grid = Grid::from_vec(self.width, self.height, cells)?.flipped_y();
+        let grid = if orientation.mirrored { grid.flipped_x() } else { grid };
+        Some(PlaceableShape { grid: grid.rotated(orientation.rotation), sprite_rotation, sprite_mirrored })
+    }
+
+    /// Every valid shape the treasure can be placed as, the authored one first
+    pub fn placeable_shapes(&self) -> Vec<PlaceableShape> {
+        self.shape_grid()
+            .map(|grid| PlaceableShape { grid, sprite_rotation: Rotation::None, sprite_mirrored: false })
+            .into_iter()
+            .chain(self.orientations.iter().filter_map(|orientation| self.transformed_shape(orientation)))
+            .collect()
+    }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 pub struct AreaInfo {
     pub levels: Vec<LevelInfo>,
+    // used by every level in the area that does not have its own drop table
+    #[serde(default)]
+    pub drop_table: Option<Vec<DropEntry>>,
+    #[serde(default)]
+    pub pity: Option<PityRule>,
+    #[serde(default)]
+    pub requires: Vec<Requirement>,
+    #[serde(default)]
+    pub audio: AreaAudio,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 pub struct LevelInfo {
     pub name: String,
     pub size: (usize, usize),
     pub stability: LevelStability,
+    /// Where the cave becomes shaky and critical, the stability profile's thresholds when not set
+    #[serde(default)]
+    pub thresholds: Option<StabilityThresholds>,
+    #[serde(default)]
+    pub drop_table: Option<Vec<DropEntry>>,
+    #[serde(default)]
+    pub requires: Vec<Requirement>,
+    /// Optional goals shown before the level starts and checked in the results
+    #[serde(default)]
+    pub objectives: Vec<Objective>,
+    /// Which tools can or must be brought, any unlocked tool when not set
+    #[serde(default)]
+    pub tools: ToolRules,
+}
+
+impl LevelInfo {
+    /// The level's own stability thresholds, falling back to those of its stability profile
+    pub fn stability_thresholds(&self) -> StabilityThresholds {
+        self.thresholds.unwrap_or_else(|| self.stability.thresholds())
+    }
+}
+
+/// Something the player has to do before a level or area can be played
+#[derive(Deserialize, Clone, Debug)]
+pub enum Requirement {
+    /// Clear the level before this one, for an area it is the last level of the area before it
+    ClearPrevious,
+    ClearLevel {
+        area: String,
+        level: String,
+    },
+    /// Find the treasure at least once, selling it afterwards still counts
+    Treasure(u32),
+    /// Earn this many stars across every level
+    Stars(u32),
+    Tool(ToolType),
+}
+
+/// How likely a treasure is to be picked compared to the rest of the drop table
+#[derive(Deserialize, Clone, Debug)]
+pub struct DropEntry {
+    pub treasure: u32,
+    pub weight: u32,
+}
+
+/// Guarantees a treasure of at least `rarity` is placed if one has not been found in the last `within` clears.
+/// Only clears count towards it, failing or leaving early does not move the streak either way so quitting runs
+/// cannot be used to force a rare drop
+#[derive(Deserialize, Clone, Debug)]
+pub struct PityRule {
+    pub rarity: Rarity,
+    pub within: u32,
+}
+
+impl PityRule {
+    /// Whether the next expedition has to place a treasure of the pity rarity after `dry_streak` clears without one
+    pub fn is_due(&self, dry_streak: u32) -> bool {
+        dry_streak + 1 >= self.within
+    }
+
+    /// The dry streak after a clear where treasures of the `found` rarities were dug up
+    pub fn next_streak(&self, dry_streak: u32, found: &[Rarity]) -> u32 {
+        if found.iter().any(|rarity| *rarity >= self.rarity) {
+            0
+        } else {
+            dry_streak + 1
+        }
+    }
+}
+
+impl LevelDb {
+    /// The drop table for the level, falling back to the area's, `None` when neither has one
+    pub fn drop_table(&self, area: &str, level_idx: usize) -> Option<&[DropEntry]> {
+        let area = self.get(area)?;
+        area.levels.get(level_idx).and_then(|level| level.drop_table.as_deref()).or(area.drop_table.as_deref())
+    }
+}
+
+impl TreasureDb {
+    pub fn get_by_id(&self, id: u32) -> Option<&TreasureInfo> {
+        self.iter().find(|treasure| treasure.id == id)
+    }
+}
+
+/// Music and background sounds that play while in the area
+#[derive(Deserialize, Clone, Default, Debug)]
+pub struct AreaAudio {
+    /// Looping tracks played together, each one fades in once the intensity reaches its own
+    #[serde(default)]
+    pub music: Vec<MusicLayer>,
+    #[serde(default)]
+    pub ambience: Vec<AmbientSound>,
+}
+
+/// A looping music track, intensity goes from 0 to 1 as the stability of an expedition runs out
+#[derive(Deserialize, Clone, Debug)]
+pub struct MusicLayer {
+    /// Path to the audio file inside of the assets folder
+    pub path: String,
+    #[serde(default)]
+    pub intensity: f32,
+    #[serde(default = "full_volume")]
+    pub volume: f32,
 }
 
-const AREA_INFO_PATH: &str = "assets/levels.json5";
-const TREASURE_PATH: &str = "assets/treasures.json5";
+fn full_volume() -> f32 {
+    1.0
+}
+
+/// A sound played over and over again at random times, like water dripping or the cave rumbling
+#[derive(Deserialize, Clone, Debug)]
+pub struct AmbientSound {
+    pub clips: Vec<SoundClip>,
+    /// Range of seconds between each time it plays
+    pub every: (f32, f32),
+    #[serde(default = "no_variation")]
+    pub volume: (f32, f32),
+    #[serde(default = "no_variation")]
+    pub pitch: (f32, f32),
+    /// Only plays once the intensity reaches this
+    #[serde(default)]
+    pub intensity: f32,
+    /// How much shorter the wait gets at full intensity, 0.5 halves it
+    #[serde(default)]
+    pub busier: f32,
+}
+
+/// How each sound cue should be played
+#[derive(Asset, Resource, TypePath, Deserialize, Default, Clone, Deref)]
+#[serde(transparent)]
+pub struct SoundBank(Vec<SoundDef>);
+
+impl SoundBank {
+    pub fn get(&self, cue: SoundCue) -> Option<&SoundDef> {
+        self.iter().find(|def| def.cue == cue)
+    }
+}
+
+/// Something happening in the game that has a sound
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SoundCue {
+    /// A rock was hit but is still standing
+    RockHit,
+    RockBreak,
+    /// A broken rock had part of a treasure under it
+    TreasureExposed,
+    /// The border around the grid was hit, it can't be mined
+    Bedrock,
+    /// Only already broken tiles were hit
+    Empty,
+    /// Stinger for the last part of a treasure being dug up
+    TreasureFound,
+    /// Stinger for the last of the stability running out
+    CaveIn,
+    StabilityShaky,
+    StabilityCritical,
+    /// A button was pressed
+    UiClick,
+    /// The mouse moved onto a button
+    UiHover,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct SoundDef {
+    pub cue: SoundCue,
+    /// One is picked at random each time the cue plays
+    pub clips: Vec<SoundClip>,
+    /// Range the volume is picked from
+    #[serde(default = "no_variation")]
+    pub volume: (f32, f32),
+    /// Range the playback rate is picked from, lower rates are lower pitched
+    #[serde(default = "no_variation")]
+    pub pitch: (f32, f32),
+    /// Added to the playback rate for each point of hp the hit rock has left
+    #[serde(default)]
+    pub pitch_per_hp: f32,
+    /// Most copies of the cue that can play at once, the oldest is cut off to make room for a new one
+    #[serde(default = "default_max_voices")]
+    pub max_voices: usize,
+    /// How much the music is turned down for a moment when the cue plays, 1 silences it
+    #[serde(default)]
+    pub duck: f32,
+}
+
+fn default_max_voices() -> usize {
+    4
+}
+
+fn no_variation() -> (f32, f32) {
+    (1.0, 1.0)
+}
+
+/// Audio files the sound bank can use, named by their asset key.
+/// Only the rock impacts exist for now, a new clip also needs its key in the dynamic asset collection and a handle
+/// in `SoundAssets`
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SoundClip {
+    RockImpact1,
+    RockImpact2,
+    RockImpact3,
+    RockImpact4,
+    RockImpact5,
+}
+
+/// Prices of everything the shop sells, treasures are sold for their own value instead
+#[derive(Asset, Resource, TypePath, Deserialize, Default, Clone)]
+pub struct ShopDb {
+    #[serde(default)]
+    pub upgrades: Vec<UpgradePrices>,
+    /// Pickaxe rotations that start locked and can be bought, in the order the shop lists them
+    #[serde(default)]
+    pub unlocks: Vec<UnlockPrice>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct UpgradePrices {
+    pub kind: UpgradeKind,
+    /// Cost of each level of the upgrade, the upgrade is maxed out after the last one
+    pub costs: Vec<u32>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct UnlockPrice {
+    pub rotation: PickaxeRotation,
+    pub cost: u32,
+}
+
+impl ShopDb {
+    /// Cost of each level of the upgrade, empty when the shop does not sell it
+    pub fn upgrade_costs(&self, kind: UpgradeKind) -> &[u32] {
+        self.upgrades.iter().find(|prices| prices.kind == kind).map_or(&[], |prices| &prices.costs)
+    }
+
+    /// Price of the pickaxe rotation, `None` for ones that cannot be bought
+    pub fn unlock_cost(&self, rotation: PickaxeRotation) -> Option<u32> {
+        self.unlocks.iter().find(|unlock| unlock.rotation == rotation).map(|unlock| unlock.cost)
+    }
+}
+
+/// Loads any of the json5 data files into the asset `A`
+pub struct Json5Loader<A> {
+    extensions: &'static [&'static str],
+    _asset: PhantomData<fn() -> A>,
+}
+
+impl<A> Json5Loader<A> {
+    fn new(extensions: &'static [&'static str]) -> Self {
+        Self { extensions, _asset: PhantomData }
+    }
+}
+
+#[derive(Debug)]
+pub enum DataLoadError {
+    Io(std::io::Error),
+    Json5(json5::Error),
+}
+
+impl Display for DataLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataLoadError::Io(e) => write!(f, "could not read data file: {e}"),
+            DataLoadError::Json5(e) => write!(f, "data file had bad data, look into it: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for DataLoadError {}
+
+impl<A> AssetLoader for Json5Loader<A>
+where
+    A: Asset + for<'de> Deserialize<'de>,
+{
+    type Asset = A;
+    type Settings = ();
+    type Error = DataLoadError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<A, DataLoadError>> {
+        Box::pin(async move {
+            let mut data = String::new();
+            reader.read_to_string(&mut data).await.map_err(DataLoadError::Io)?;
+            parse_data(&data)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
 
-pub fn load_area_info_into_db() {
-    let ai_str = fs::read_to_string(AREA_INFO_PATH).unwrap();
-    let area_info = json5::from_str(&ai_str).expect(&format!("{AREA_INFO_PATH} had bad data, look into it"));
-    let _ = LEVEL_DB.set(area_info);
+/// Parses the contents of one of the json5 data files
+pub fn parse_data<A: for<'de> Deserialize<'de>>(data: &str) -> Result<A, DataLoadError> {
+    json5::from_str(data).map_err(DataLoadError::Json5)
 }
 
-pub fn load_treasures_into_db() {
-    let treasure_str = fs::read_to_string(TREASURE_PATH).unwrap();
-    let treasure_info = json5::from_str(&treasure_str).expect(&format!("{TREASURE_PATH} had bad data, look into it"));
-    let _ = TREASURE_DB.set(treasure_info);
+/// Copies the data asset into its resource whenever it is loaded or changed on disk
+fn sync_data_asset<A: Asset + Resource + Clone>(
+    mut ev_asset: EventReader<AssetEvent<A>>,
+    assets: Res<Assets<A>>,
+    mut db: ResMut<A>,
+) {
+    for ev in ev_asset.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = ev else {
+            continue;
+        };
+        let Some(data) = assets.get(*id) else {
+            continue;
+        };
+        *db = data.clone();
+        info!("reloaded {}", A::short_type_path());
+    }
 }
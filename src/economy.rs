@@ -0,0 +1,117 @@
+use std::fmt::Display;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data_read::{ShopDb, TreasureDb},
+    save::Progress,
+    tools::{PickaxeRotation, ToolType, UpgradeKind},
+};
+
+pub struct EconomyPlugin;
+
+impl Plugin for EconomyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ShopAction>().add_systems(Update, handle_shop_actions.run_if(on_event::<ShopAction>()));
+    }
+}
+
+/// Coins the player has to spend at the shop, kept in the save
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct Wallet {
+    pub coins: u32,
+}
+
+impl Wallet {
+    /// Takes the coins out of the wallet, false if there were not enough to pay
+    pub fn spend(&mut self, amt: u32) -> bool {
+        if self.coins < amt {
+            return false;
+        }
+        self.coins -= amt;
+        true
+    }
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub enum ShopAction {
+    Sell { treasure: u32 },
+    Upgrade(UpgradeKind),
+    Unlock(PickaxeRotation),
+}
+
+/// Why a shop action could not go through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShopError {
+    UnknownTreasure,
+    NoneOwned,
+    MaxedOut,
+    NotForSale,
+    AlreadyUnlocked,
+    TooExpensive { cost: u32 },
+}
+
+impl Display for ShopError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShopError::UnknownTreasure => write!(f, "the treasure does not exist"),
+            ShopError::NoneOwned => write!(f, "none are owned"),
+            ShopError::MaxedOut => write!(f, "it is already maxed out"),
+            ShopError::NotForSale => write!(f, "it cannot be bought"),
+            ShopError::AlreadyUnlocked => write!(f, "it is already unlocked"),
+            ShopError::TooExpensive { cost } => write!(f, "it costs {cost} coins"),
+        }
+    }
+}
+
+impl ShopAction {
+    /// Sells or buys with the player's progress, nothing is changed when it can't go through
+    pub fn apply(&self, progress: &mut Progress, tdb: &TreasureDb, shop: &ShopDb) -> Result<(), ShopError> {
+        match *self {
+            ShopAction::Sell { treasure } => {
+                let info = tdb.get_by_id(treasure).ok_or(ShopError::UnknownTreasure)?;
+                if !progress.trove.take_one(treasure) {
+                    return Err(ShopError::NoneOwned);
+                }
+                progress.wallet.coins += info.value;
+                info!("sold {} for {} coins", info.name, info.value);
+            }
+            ShopAction::Upgrade(kind) => {
+                let cost = progress.upgrades.next_cost(kind, shop).ok_or(ShopError::MaxedOut)?;
+                if !progress.wallet.spend(cost) {
+                    return Err(ShopError::TooExpensive { cost });
+                }
+                progress.upgrades.add_level(kind);
+                info!("bought {} level {}", kind.name(), progress.upgrades.level(kind));
+            }
+            ShopAction::Unlock(rotation) => {
+                let tool = ToolType::Pickaxe { rotation };
+                let cost = shop.unlock_cost(rotation).ok_or(ShopError::NotForSale)?;
+                if progress.tools.is_unlocked(&tool) {
+                    return Err(ShopError::AlreadyUnlocked);
+                }
+                if !progress.wallet.spend(cost) {
+                    return Err(ShopError::TooExpensive { cost });
+                }
+                progress.tools.unlock(&tool);
+                info!("unlocked {:?}", tool);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn handle_shop_actions(
+    mut ev_shop: EventReader<ShopAction>,
+    mut progress: ResMut<Progress>,
+    tdb: Res<TreasureDb>,
+    shop: Res<ShopDb>,
+) {
+    for ev in ev_shop.read() {
+        if let Err(e) = ev.apply(&mut progress, &tdb, &shop) {
+            warn!("could not {:?} since {}", ev, e);
+        }
+    }
+}
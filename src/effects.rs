@@ -0,0 +1,246 @@
+use bevy::prelude::*;
+use rand::{thread_rng, Rng};
+
+use crate::{
+    camera::MainCamera,
+    expedition::{ExpeditionPersist, ExpeditionPhase, PauseState},
+    mining::{MiningGrid, TileBroken},
+    point::{tile_to_world, IPoint},
+    settings::{KeyAction, Settings},
+    stability::{CaveIn, Stability, StabilityDamage, StabilityLevel, StabilityWarning},
+    AppState,
+};
+
+const PARTICLE_Z: f32 = 40.0;
+/// Furthest the camera moves away from where it should be at full trauma, in pixels
+const MAX_SHAKE_PX: f32 = 6.0;
+/// Trauma lost per second
+const TRAUMA_DECAY: f32 = 1.2;
+/// Trauma added by a single swing that used up all of the starting stability
+const DAMAGE_TRAUMA: f32 = 4.0;
+/// Most trauma a single swing can add so one big hit never feels like a cave-in
+const MAX_DAMAGE_TRAUMA: f32 = 0.35;
+const DUST_PER_TILE: usize = 6;
+const DUST_COLOR: Color = Color::rgb(0.55, 0.48, 0.4);
+const DEBRIS_COLOR: Color = Color::rgb(0.35, 0.3, 0.28);
+const DEBRIS_GRAVITY: f32 = -260.0;
+
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraShake>()
+            .init_resource::<DebrisTimer>()
+            .add_systems(
+                Update,
+                (
+                    (shake_on_stability_damage, shake_on_stability_warning, shake_on_cave_in),
+                    spawn_tile_dust,
+                    spawn_falling_debris
+                        .run_if(motion_allowed)
+                        .run_if(in_state(PauseState::Running))
+                        .run_if(in_state(ExpeditionPhase::Mining).or_else(in_state(ExpeditionPhase::Collapsing))),
+                )
+                    .run_if(in_state(AppState::Expedition))
+                    .before(apply_camera_shake),
+            )
+            .add_systems(Update, (apply_camera_shake, toggle_reduced_motion))
+            .add_systems(Update, update_particles.run_if(in_state(PauseState::Running)))
+            .add_systems(OnExit(AppState::Expedition), reset_effects);
+    }
+}
+
+fn motion_allowed(settings: Res<Settings>) -> bool {
+    !settings.reduced_motion
+}
+
+/// Shakes the camera while there is trauma, the shake grows with the square of it so small hits stay subtle
+#[derive(Resource, Default)]
+pub struct CameraShake {
+    trauma: f32,
+}
+
+impl CameraShake {
+    /// Adds to the trauma, it is capped at 1
+    pub fn add_trauma(&mut self, amt: f32) {
+        self.trauma = (self.trauma + amt).min(1.0);
+    }
+}
+
+/// How far the shake has moved the camera, taken back off before the next shake is applied
+#[derive(Component, Default)]
+pub struct ShakeOffset(pub Vec2);
+
+/// A short lived sprite that drifts, falls and fades out
+#[derive(Component)]
+struct Particle {
+    velocity: Vec2,
+    gravity: f32,
+    life: Timer,
+}
+
+/// Time until the next piece of debris falls from the ceiling
+#[derive(Resource, Default)]
+struct DebrisTimer(Timer);
+
+fn shake_on_stability_damage(
+    mut ev_damage: EventReader<StabilityDamage>,
+    mut shake: ResMut<CameraShake>,
+    stability: Res<Stability>,
+) {
+    if stability.start <= 0 {
+        return;
+    }
+    for ev in ev_damage.read() {
+        let trauma = ev.amount() as f32 / stability.start as f32 * DAMAGE_TRAUMA;
+        shake.add_trauma(trauma.min(MAX_DAMAGE_TRAUMA));
+    }
+}
+
+fn shake_on_stability_warning(mut ev_warning: EventReader<StabilityWarning>, mut shake: ResMut<CameraShake>) {
+    for StabilityWarning(level) in ev_warning.read() {
+        shake.add_trauma(match level {
+            StabilityLevel::Stable => 0.0,
+            StabilityLevel::Shaky => 0.4,
+            StabilityLevel::Critical => 0.7,
+        });
+    }
+}
+
+fn shake_on_cave_in(mut ev_cave_in: EventReader<CaveIn>, mut shake: ResMut<CameraShake>) {
+    if ev_cave_in.read().count() > 0 {
+        shake.add_trauma(1.0);
+    }
+}
+
+fn apply_camera_shake(
+    mut q_camera: Query<(&mut Transform, &mut ShakeOffset), With<MainCamera>>,
+    mut shake: ResMut<CameraShake>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    let Ok((mut transform, mut offset)) = q_camera.get_single_mut() else {
+        return;
+    };
+    if shake.trauma <= 0.0 && offset.0 == Vec2::ZERO {
+        return;
+    }
+    transform.translation -= offset.0.extend(0.0);
+    offset.0 = Vec2::ZERO;
+    if !settings.reduced_motion {
+        let mut rng = thread_rng();
+        let strength = MAX_SHAKE_PX * shake.trauma * shake.trauma;
+        offset.0 = Vec2::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0)) * strength;
+        transform.translation += offset.0.extend(0.0);
+    }
+    shake.trauma = (shake.trauma - TRAUMA_DECAY * time.delta_seconds()).max(0.0);
+}
+
+/// Puffs of dust where a tile broke, they stay put instead of drifting when motion is reduced
+fn spawn_tile_dust(mut commands: Commands, mut ev_broken: EventReader<TileBroken>, settings: Res<Settings>) {
+    let mut rng = thread_rng();
+    for ev in ev_broken.read() {
+        for _ in 0..DUST_PER_TILE {
+            let jitter = Vec2::new(rng.gen_range(-6.0..6.0), rng.gen_range(-6.0..6.0));
+            let velocity = if settings.reduced_motion {
+                Vec2::ZERO
+            } else {
+                Vec2::new(rng.gen_range(-20.0..20.0), rng.gen_range(5.0..30.0))
+            };
+            spawn_particle(
+                &mut commands,
+                ev.pos + jitter,
+                rng.gen_range(1.5..3.5),
+                DUST_COLOR.with_a(0.8),
+                Particle {
+                    velocity,
+                    gravity: -15.0,
+                    life: Timer::from_seconds(rng.gen_range(0.4..0.8), TimerMode::Once),
+                },
+            );
+        }
+    }
+}
+
+/// Bits of the ceiling fall into the level once it gets shaky, more often when critical
+fn spawn_falling_debris(
+    mut commands: Commands,
+    mut debris_timer: ResMut<DebrisTimer>,
+    q_grid: Query<&MiningGrid>,
+    stability: Res<Stability>,
+    time: Res<Time>,
+) {
+    let interval = match stability.level() {
+        StabilityLevel::Stable => return,
+        StabilityLevel::Shaky => 1.5,
+        StabilityLevel::Critical => 0.35,
+    };
+    if !debris_timer.0.tick(time.delta()).finished() {
+        return;
+    }
+    let mut rng = thread_rng();
+    debris_timer.0 = Timer::from_seconds(interval * rng.gen_range(0.5..1.5), TimerMode::Once);
+    let Ok(grid) = q_grid.get_single() else {
+        return;
+    };
+
+    let (width, height) = (grid.rock_tiles.width() as i32, grid.rock_tiles.height() as i32);
+    let start = tile_to_world(IPoint::new(rng.gen_range(0..width), height));
+    let end = tile_to_world(IPoint::new(0, -1));
+    // long enough to fall past the bottom of the grid from rest
+    let fall_time = (2.0 * (start.y - end.y) / -DEBRIS_GRAVITY).sqrt();
+    spawn_particle(
+        &mut commands,
+        start,
+        rng.gen_range(2.0..5.0),
+        DEBRIS_COLOR,
+        Particle {
+            velocity: Vec2::new(rng.gen_range(-8.0..8.0), 0.0),
+            gravity: DEBRIS_GRAVITY,
+            life: Timer::from_seconds(fall_time, TimerMode::Once),
+        },
+    );
+}
+
+fn spawn_particle(commands: &mut Commands, pos: Vec2, size: f32, color: Color, particle: Particle) {
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite { color, custom_size: Some(Vec2::splat(size)), ..default() },
+            transform: Transform::from_translation(pos.extend(PARTICLE_Z)),
+            ..default()
+        },
+        particle,
+        ExpeditionPersist,
+    ));
+}
+
+fn update_particles(
+    mut commands: Commands,
+    mut q_particles: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut particle, mut transform, mut sprite) in q_particles.iter_mut() {
+        if particle.life.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let gravity = particle.gravity;
+        particle.velocity.y += gravity * dt;
+        transform.translation += (particle.velocity * dt).extend(0.0);
+        let alpha = sprite.color.a();
+        sprite.color.set_a(alpha.min(particle.life.percent_left() * 2.0));
+    }
+}
+
+fn toggle_reduced_motion(mut settings: ResMut<Settings>, keeb: Res<Input<KeyCode>>) {
+    if settings.keybinds.just_pressed(KeyAction::ToggleReducedMotion, &keeb) {
+        settings.reduced_motion = !settings.reduced_motion;
+        info!("reduced motion is now {}", settings.reduced_motion);
+    }
+}
+
+fn reset_effects(mut shake: ResMut<CameraShake>, mut debris_timer: ResMut<DebrisTimer>) {
+    *shake = CameraShake::default();
+    *debris_timer = DebrisTimer::default();
+}
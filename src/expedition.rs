@@ -1,14 +1,18 @@
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr};
 
 use bevy::{
+    ecs::system::SystemParam,
     log::{error, warn},
     prelude::*,
 };
+use rand::{rngs::StdRng, SeedableRng};
 
 use crate::{
     camera::CameraUpdate,
-    data_read::LEVEL_DB,
-    stability::{LevelStability, Stability},
+    data_read::LevelInfo,
+    progression::{RequirementCtx, Requirements},
+    settings::{KeyAction, Settings, SettingsMenu},
+    stability::{CaveIn, Stability},
     AppState,
 };
 
@@ -19,16 +23,49 @@ impl Plugin for ExpeditionPlugin {
         app.add_event::<LevelChange>()
             .add_event::<InitExpedition>()
             .add_event::<ExpeditionLeave>()
-            .init_resource::<ExpeditionStatus>()
-            .add_systems(Update, (setup_expedition, stop_expedition).run_if(in_area_state))
+            .add_event::<PhaseChange>()
+            .init_resource::<ActiveLevel>()
+            .init_resource::<PendingLevelChange>()
+            .add_state::<ExpeditionPhase>()
+            .add_state::<PauseState>()
+            .add_state::<LeaveConfirm>()
+            .add_systems(Update, (setup_expedition).run_if(in_area_state))
+            .add_systems(Update, setup_expedition.run_if(in_state(AppState::LevelTransition)))
+            .add_systems(
+                Update,
+                toggle_pause
+                    .run_if(in_state(ExpeditionPhase::Mining).or_else(in_state(ExpeditionPhase::Collapsing)))
+                    .run_if(in_state(SettingsMenu::Closed))
+                    .run_if(in_state(LeaveConfirm::Closed)),
+            )
             .add_systems(
                 Update,
                 (handle_leave_button).run_if(in_state(AppState::Expedition)).run_if(on_event::<ExpeditionLeave>()),
             )
-            .add_systems(Update, (leave_expedition).run_if(in_state(AppState::Expedition)))
-            .add_systems(OnExit(AppState::Expedition), cleanup_expedition);
+            .add_systems(
+                Update,
+                (change_phase).run_if(in_state(AppState::Expedition)).run_if(on_event::<PhaseChange>()),
+            )
+            .add_systems(OnEnter(AppState::Expedition), start_intro)
+            .add_systems(
+                Update,
+                (collapse_on_cave_in).run_if(in_state(ExpeditionPhase::Mining)).run_if(on_event::<CaveIn>()),
+            )
+            .add_systems(OnEnter(ExpeditionPhase::Collapsing), start_collapse)
+            .add_systems(
+                Update,
+                (finish_collapse).run_if(in_state(ExpeditionPhase::Collapsing)).run_if(in_state(PauseState::Running)),
+            )
+            .add_systems(OnEnter(ExpeditionPhase::Leaving), leave_expedition)
+            .add_systems(
+                Update,
+                (transition_level).run_if(in_state(AppState::Expedition)).run_if(on_event::<LevelChange>()),
+            )
+            .add_systems(OnExit(AppState::Expedition), (cleanup_expedition, end_phases, unpause, close_leave_confirm));
 
-        debug_assert!(debug_leave_expedition(app));
+        // skips the leave confirmation, so only in debug builds
+        #[cfg(debug_assertions)]
+        app.add_systems(Update, (stop_expedition).run_if(in_state(AppState::Expedition)));
     }
 }
 
@@ -36,20 +73,79 @@ pub fn in_area_state(app_state: Res<State<AppState>>) -> bool {
     matches!(app_state.to_owned(), AppState::AreaViewer { .. })
 }
 
-#[derive(Resource, Default)]
-pub enum ExpeditionStatus {
+/// Whether the expedition is paused, mining and everything that wears down the stability stop while paused
+#[derive(States, PartialEq, Eq, Hash, Clone, Copy, Debug, Default)]
+pub enum PauseState {
     #[default]
+    Running,
+    Paused,
+}
+
+/// Where the current expedition is in its lifecycle, only changed through `PhaseChange` so every move is checked
+#[derive(States, PartialEq, Eq, Hash, Clone, Copy, Debug, Default)]
+pub enum ExpeditionPhase {
+    /// Not in an expedition
+    #[default]
+    Inactive,
+    /// The level has just been set up and mining hasn't started yet
+    Intro,
     Mining,
+    /// The stability ran out and the cave is coming down
+    Collapsing,
+    /// Every treasure was found
     Cleared,
+    /// The cave came down before every treasure was found
+    Failed,
+    /// The player chose to leave before every treasure was found, they only keep what was dug up
+    LeftEarly,
     Leaving,
 }
 
+impl ExpeditionPhase {
+    /// Whether the expedition can go straight from this phase to `next`
+    pub fn can_move_to(&self, next: ExpeditionPhase) -> bool {
+        use ExpeditionPhase::*;
+        matches!(
+            (self, next),
+            (_, Inactive)
+                | (Inactive, Intro)
+                | (Intro, Mining)
+                | (Mining, Collapsing | Cleared | LeftEarly | Leaving)
+                | (Collapsing, Failed | Leaving)
+                | (Cleared | Failed | LeftEarly, Leaving)
+        )
+    }
+
+    /// The expedition has ended one way or another and only leaving is left to do
+    pub fn is_over(&self) -> bool {
+        matches!(self, ExpeditionPhase::Cleared | ExpeditionPhase::Failed | ExpeditionPhase::LeftEarly)
+    }
+}
+
+/// Asks for the expedition to move to another phase, moves that don't make sense from the current phase are ignored
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PhaseChange(pub ExpeditionPhase);
+
+/// How long the cave takes to come down before the expedition is failed
+const COLLAPSE_SECS: f32 = 1.5;
+
+#[derive(Resource)]
+struct CollapseTimer(Timer);
+
+/// Asks the player if they really want to leave when there are still treasures in the rock
+#[derive(States, PartialEq, Eq, Hash, Clone, Copy, Debug, Default)]
+pub enum LeaveConfirm {
+    #[default]
+    Closed,
+    Open,
+}
+
 #[derive(Event, Default)]
 pub struct ExpeditionLeave {}
 
-#[derive(PartialEq, Eq, Hash, Clone, Debug)]
-#[allow(unused)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Default)]
 pub enum Area {
+    #[default]
     TheCaves,
     TheCollapse,
 }
@@ -64,55 +160,161 @@ impl Display for Area {
     }
 }
 
+impl Area {
+    pub const ALL: [Area; 2] = [Area::TheCaves, Area::TheCollapse];
+}
+
+impl FromStr for Area {
+    type Err = String;
+
+    /// Accepts the display name or a kebab case version of it, ie "The Caves" or "the-caves"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let wanted = s.trim().to_lowercase().replace('-', " ");
+        Area::ALL
+            .into_iter()
+            .find(|area| area.to_string().to_lowercase() == wanted)
+            .ok_or_else(|| format!("unknown area {s}"))
+    }
+}
+
 #[derive(Event, Debug)]
 pub struct InitExpedition {
     pub size_x: usize,
     pub size_y: usize,
+    pub seed: u64,
+}
+
+impl InitExpedition {
+    /// Rng for a single part of the expedition generation, `stream` keeps each part independent of the others
+    pub fn rng(&self, stream: u64) -> StdRng {
+        StdRng::seed_from_u64(self.seed ^ stream.rotate_left(32))
+    }
+}
+
+/// A level change from inside an expedition, held on to until `LevelTransition` has cleaned up the old expedition
+/// since the event itself may be gone by then
+#[derive(Resource, Default)]
+struct PendingLevelChange(Option<LevelChange>);
+
+/// The level the current, or most recent, expedition was started from
+#[derive(Resource, Default, Clone, Debug)]
+pub struct ActiveLevel {
+    pub area: Area,
+    pub level_idx: usize,
+    pub seed: u64,
 }
 
 /// Marks an entity as something that persists only for the lifetime of the current expedition
 #[derive(Component)]
 pub struct ExpeditionPersist;
 
-#[derive(Event)]
+#[derive(Event, Clone, Debug)]
 pub struct LevelChange {
     pub area: Area,
     pub level_idx: usize,
+    /// Seed for generating the expedition, a random one is picked when none is given
+    pub seed: Option<u64>,
+    /// Overrides the level's starting stability, used for testing levels
+    pub stability: Option<i32>,
+    /// Starts the level even if it is still locked, only for testing levels from the command line
+    pub ignore_requirements: bool,
+}
+
+impl LevelChange {
+    pub fn new(area: Area, level_idx: usize) -> Self {
+        Self { area, level_idx, seed: None, stability: None, ignore_requirements: false }
+    }
+}
+
+/// Why a level change could not start an expedition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelChangeError {
+    MissingArea,
+    MissingLevel,
+    Locked,
+}
+
+impl Display for LevelChangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LevelChangeError::MissingArea => write!(f, "the area has no levels"),
+            LevelChangeError::MissingLevel => write!(f, "the level does not exist"),
+            LevelChangeError::Locked => write!(f, "the level is still locked"),
+        }
+    }
+}
+
+impl LevelChange {
+    /// The level to start, checking that it exists and has been unlocked.
+    /// Replaying the level last played is always allowed since it was already let through once
+    pub fn find_level<'a>(
+        &self,
+        ctx: &RequirementCtx<'a>,
+        last_played: &ActiveLevel,
+    ) -> Result<&'a LevelInfo, LevelChangeError> {
+        let area = ctx.level_db.get(&self.area.to_string()).ok_or(LevelChangeError::MissingArea)?;
+        let level = area.levels.get(self.level_idx).ok_or(LevelChangeError::MissingLevel)?;
+        let is_replay = self.area == last_played.area && self.level_idx == last_played.level_idx;
+        if !self.ignore_requirements && !is_replay && !ctx.is_level_unlocked(&self.area, self.level_idx) {
+            return Err(LevelChangeError::Locked);
+        }
+        Ok(level)
+    }
+
+    /// Where to go when the level can't be started, `None` to stay put.
+    /// Coming from another expedition the old one is already cleaned up, so it falls back to the area viewer
+    pub fn fallback_state(&self, current: &AppState) -> Option<AppState> {
+        matches!(current, AppState::LevelTransition).then(|| AppState::AreaViewer { curr_area: self.area.clone() })
+    }
+}
+
+/// Everything set up from the level info when an expedition starts
+#[derive(SystemParam)]
+struct ExpeditionStart<'w> {
+    ev_init_mining_grid: EventWriter<'w, InitExpedition>,
+    ev_cam_update: EventWriter<'w, CameraUpdate>,
+    stability: ResMut<'w, Stability>,
+    active_level: ResMut<'w, ActiveLevel>,
 }
 
 fn setup_expedition(
     mut ev_level_change: EventReader<LevelChange>,
+    mut pending: ResMut<PendingLevelChange>,
+    app_state: Res<State<AppState>>,
     mut next_state: ResMut<NextState<AppState>>,
-    mut ev_init_mining_grid: EventWriter<InitExpedition>,
-    mut ev_cam_update: EventWriter<CameraUpdate>,
-    mut stability: ResMut<Stability>,
-    mut expedition_status: ResMut<ExpeditionStatus>,
+    mut start: ExpeditionStart,
+    requirements: Requirements,
 ) {
-    // only process 1st level change
-    let Some(ev) = ev_level_change.read().next() else {
-        return;
+    // only process 1st level change, between levels it was already picked up by `transition_level`
+    let ev = match app_state.get() {
+        AppState::LevelTransition => pending.0.take(),
+        _ => ev_level_change.read().next().cloned(),
     };
-
-    // take area and level and get the level info from level DB
-    let Some(level_db) = LEVEL_DB.get() else {
-        error!("did not find the level db and it was not initialized");
+    let Some(ev) = ev else {
         return;
     };
 
-    // use level info to create generation events (mining grid, stability, treasures)
-    let Some(info) = level_db.get(&ev.area.to_string()) else {
-        warn!("No level found for {} in area {}", ev.level_idx, ev.area);
-        return;
+    // take area and level and get the level info from level DB, use level info to create generation events (mining grid, stability, treasures)
+    let level = match ev.find_level(&requirements.ctx(), &start.active_level) {
+        Ok(level) => level,
+        Err(e) => {
+            error!("cannot start level {} in area {} since {}", ev.level_idx, ev.area, e);
+            if let Some(fallback) = ev.fallback_state(app_state.get()) {
+                next_state.set(fallback);
+            }
+            return;
+        }
     };
-
-    let level = &info.levels[ev.level_idx];
-    ev_init_mining_grid.send(InitExpedition { size_x: level.size.0, size_y: level.size.1 });
-    ev_cam_update.send(CameraUpdate { width: level.size.0 as f32, height: level.size.1 as f32, scale: 2.0 });
-    *stability = match level.stability {
-        LevelStability::Normal => Stability::new(10000),
+    let seed = ev.seed.unwrap_or_else(rand::random);
+    info!("starting {} with seed {}", level.name, seed);
+    start.ev_init_mining_grid.send(InitExpedition { size_x: level.size.0, size_y: level.size.1, seed });
+    *start.active_level = ActiveLevel { area: ev.area.clone(), level_idx: ev.level_idx, seed };
+    start.ev_cam_update.send(CameraUpdate { width: level.size.0 as f32, height: level.size.1 as f32, scale: 2.0 });
+    let stability = Stability::new(level.stability).with_thresholds(level.stability_thresholds());
+    *start.stability = match ev.stability {
+        Some(start_stability) => stability.with_start(start_stability),
+        None => stability,
     };
-    *expedition_status = ExpeditionStatus::Mining;
-
     // switch state
     next_state.set(AppState::Expedition);
 }
@@ -123,31 +325,127 @@ fn cleanup_expedition(mut commands: Commands, q_expedition_entities: Query<Entit
     }
 }
 
-fn leave_expedition(expedition_status: ResMut<ExpeditionStatus>, mut next_state: ResMut<NextState<AppState>>) {
-    if expedition_status.is_changed() && matches!(*expedition_status, ExpeditionStatus::Leaving) {
-        // TODO: update to switch back to the area that the player is currently in
-        next_state.set(AppState::AreaViewer { curr_area: crate::expedition::Area::TheCaves });
+/// A level change sent from inside an expedition goes through `LevelTransition` so the current one is cleaned up
+/// first, `setup_expedition` then picks the change up from `PendingLevelChange`.
+/// Moving on to a level that is still locked is refused so the player stays on the results
+fn transition_level(
+    mut ev_level_change: EventReader<LevelChange>,
+    mut pending: ResMut<PendingLevelChange>,
+    mut next_state: ResMut<NextState<AppState>>,
+    active_level: Res<ActiveLevel>,
+    requirements: Requirements,
+) {
+    let Some(ev) = ev_level_change.read().next() else {
+        return;
+    };
+    if let Err(LevelChangeError::Locked) = ev.find_level(&requirements.ctx(), &active_level) {
+        warn!("not moving on to level {} in area {} since it is still locked", ev.level_idx, ev.area);
+        return;
+    }
+    pending.0 = Some(ev.clone());
+    next_state.set(AppState::LevelTransition);
+}
+
+/// Applies the requested phase changes in the order they were sent, a later request is checked against the phase
+/// an earlier one in the same frame already moved to
+fn change_phase(
+    mut ev_phase: EventReader<PhaseChange>,
+    phase: Res<State<ExpeditionPhase>>,
+    mut next_phase: ResMut<NextState<ExpeditionPhase>>,
+) {
+    let mut current = *phase.get();
+    for PhaseChange(next) in ev_phase.read() {
+        if current == *next {
+            continue;
+        }
+        if !current.can_move_to(*next) {
+            debug!("ignoring expedition phase change from {:?} to {:?}", current, next);
+            continue;
+        }
+        info!("expedition phase {:?} -> {:?}", current, next);
+        current = *next;
+        next_phase.set(current);
+    }
+}
+
+fn start_intro(mut ev_phase: EventWriter<PhaseChange>) {
+    ev_phase.send(PhaseChange(ExpeditionPhase::Intro));
+}
+
+fn collapse_on_cave_in(mut ev_phase: EventWriter<PhaseChange>) {
+    ev_phase.send(PhaseChange(ExpeditionPhase::Collapsing));
+}
+
+fn start_collapse(mut commands: Commands) {
+    commands.insert_resource(CollapseTimer(Timer::from_seconds(COLLAPSE_SECS, TimerMode::Once)));
+}
+
+fn finish_collapse(mut timer: ResMut<CollapseTimer>, time: Res<Time>, mut ev_phase: EventWriter<PhaseChange>) {
+    if timer.0.tick(time.delta()).just_finished() {
+        ev_phase.send(PhaseChange(ExpeditionPhase::Failed));
+    }
+}
+
+fn leave_expedition(active_level: Res<ActiveLevel>, mut next_state: ResMut<NextState<AppState>>) {
+    next_state.set(AppState::AreaViewer { curr_area: active_level.area.clone() });
+}
+
+fn end_phases(mut next_phase: ResMut<NextState<ExpeditionPhase>>) {
+    next_phase.set(ExpeditionPhase::Inactive);
+}
+
+fn toggle_pause(
+    keeb: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    pause_state: Res<State<PauseState>>,
+    mut next_pause: ResMut<NextState<PauseState>>,
+) {
+    if !settings.keybinds.just_pressed(KeyAction::Pause, &keeb) {
+        return;
     }
+    next_pause.set(match pause_state.get() {
+        PauseState::Running => PauseState::Paused,
+        PauseState::Paused => PauseState::Running,
+    });
 }
 
-/// Adds `BackSpace` key as an option to leave the expedition early
-fn debug_leave_expedition(app: &mut App) -> bool {
-    app.add_systems(Update, (stop_expedition).run_if(in_state(AppState::Expedition)));
-    true
+fn unpause(mut next_pause: ResMut<NextState<PauseState>>) {
+    next_pause.set(PauseState::Running);
 }
 
-/// Forcibly stops the expedition
-fn stop_expedition(mut expedition_status: ResMut<ExpeditionStatus>, keeb: Res<Input<KeyCode>>) {
+fn close_leave_confirm(mut next_confirm: ResMut<NextState<LeaveConfirm>>) {
+    next_confirm.set(LeaveConfirm::Closed);
+}
+
+/// Clicks on the grid only mine while mining and when there is no menu open over it
+pub fn mining_input_allowed(
+    phase: Res<State<ExpeditionPhase>>,
+    pause_state: Res<State<PauseState>>,
+    leave_confirm: Res<State<LeaveConfirm>>,
+) -> bool {
+    *phase.get() == ExpeditionPhase::Mining
+        && *pause_state.get() == PauseState::Running
+        && *leave_confirm.get() == LeaveConfirm::Closed
+}
+
+/// Forcibly stops the expedition when `BackSpace` is pressed
+#[cfg(debug_assertions)]
+fn stop_expedition(mut ev_phase: EventWriter<PhaseChange>, keeb: Res<Input<KeyCode>>) {
     if !keeb.just_pressed(KeyCode::Back) {
         return;
     }
-    *expedition_status = ExpeditionStatus::Leaving;
+    ev_phase.send(PhaseChange(ExpeditionPhase::Leaving));
 }
 
-fn handle_leave_button(mut expedition_status: ResMut<ExpeditionStatus>) {
-    *expedition_status = match *expedition_status {
-        ExpeditionStatus::Mining => ExpeditionStatus::Cleared,
-        ExpeditionStatus::Cleared => ExpeditionStatus::Leaving,
-        ExpeditionStatus::Leaving => unreachable!("shouldn't hit since we would be out of this state then"),
-    };
+/// Leaving while still mining asks first, otherwise the button takes the player out of the finished expedition
+fn handle_leave_button(
+    phase: Res<State<ExpeditionPhase>>,
+    mut ev_phase: EventWriter<PhaseChange>,
+    mut next_confirm: ResMut<NextState<LeaveConfirm>>,
+) {
+    match phase.get() {
+        ExpeditionPhase::Mining => next_confirm.set(LeaveConfirm::Open),
+        phase if phase.is_over() => ev_phase.send(PhaseChange(ExpeditionPhase::Leaving)),
+        _ => {}
+    }
 }
@@ -0,0 +1,122 @@
+mod assets;
+mod audio_events;
+mod camera;
+pub mod cli;
+mod consts;
+pub mod data_read;
+pub mod economy;
+mod effects;
+pub mod expedition;
+mod mining;
+mod music;
+pub mod point;
+pub mod progression;
+pub mod run_stats;
+pub mod save;
+pub mod settings;
+pub mod stability;
+pub mod tools;
+pub mod treasures;
+mod ui;
+
+use assets::AssetLoadPlugin;
+use audio_events::AudioEventsPlugin;
+use bevy::{
+    asset::{io::AssetSourceBuilder, AssetApp},
+    log::LogPlugin,
+    prelude::*,
+};
+use bevy_kira_audio::AudioPlugin;
+use bevy_mod_picking::DefaultPickingPlugins;
+use camera::CameraPlugin;
+use clap::Parser;
+use cli::{LaunchArgs, LaunchPlugin};
+use data_read::DataPlugin;
+use economy::EconomyPlugin;
+use effects::EffectsPlugin;
+use expedition::{Area, ExpeditionPlugin};
+use mining::MiningPlugin;
+use music::MusicPlugin;
+use progression::ProgressionPlugin;
+use run_stats::RunStatsPlugin;
+use save::SavePlugin;
+use settings::{Settings, SettingsPlugin};
+use stability::StabilityPlugin;
+use tools::ToolPlugin;
+use treasures::TreasurePlugin;
+use ui::UIPlugins;
+
+/// Builds and runs the game using the command line args
+pub fn run() {
+    let args = LaunchArgs::parse();
+    let settings = Settings::load(&args);
+
+    App::new()
+        // needs to be registered before the AssetPlugin is added
+        .register_asset_source("data", AssetSourceBuilder::platform_default(&args.data_dir.to_string_lossy(), None))
+        .add_plugins((
+            DefaultPlugins
+                .set(WindowPlugin { primary_window: Some(settings.window()), ..Default::default() })
+                .set(LogPlugin {
+                    filter: "info,wgpu_core=warn,wgpu_hal=warn,mygame=debug".into(),
+                    level: bevy::log::Level::DEBUG,
+                })
+                .set(ImagePlugin::default_nearest()),
+            AudioPlugin,
+            AudioEventsPlugin,
+            DefaultPickingPlugins,
+            CameraPlugin,
+            MiningPlugin,
+            ToolPlugin,
+            TreasurePlugin,
+            AssetLoadPlugin { start: args.start_state() },
+            DataPlugin,
+            StabilityPlugin,
+            ExpeditionPlugin,
+            UIPlugins,
+            LaunchPlugin(args),
+        ))
+        .add_plugins((
+            SavePlugin,
+            EconomyPlugin,
+            ProgressionPlugin,
+            RunStatsPlugin,
+            EffectsPlugin,
+            MusicPlugin,
+            SettingsPlugin { settings },
+        ))
+        .add_state::<AppState>()
+        .run();
+}
+
+#[derive(States, PartialEq, Eq, Hash, Clone, Debug, Default)]
+pub enum AppState {
+    #[default]
+    AssetLoading,
+    // TODO: change area to resource type
+    AreaViewer {
+        curr_area: Area,
+    },
+    Expedition,
+    /// Passed through when going straight from one expedition to another, leaving `Expedition` is what cleans
+    /// up the old one since a state can't transition to itself
+    LevelTransition,
+    /// Gallery of every treasure, remembers the area it was opened from
+    Collection {
+        curr_area: Area,
+    },
+    /// Sell treasures and buy tools, remembers the area it was opened from
+    Shop {
+        curr_area: Area,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, SystemSet)]
+enum SystemOrder {
+    _Input, // anything to do with getting input from the player
+    Logic,  // anything to do with changing state
+    Render, // anything to do with drawing state
+}
+
+const SPRITE_PX_X: u32 = 16;
+const SPRITE_PX_Y: u32 = SPRITE_PX_X;
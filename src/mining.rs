@@ -1,4 +1,7 @@
-use bevy::{prelude::*, utils::HashSet, window::PrimaryWindow};
+use std::iter::once;
+
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashSet, window::PrimaryWindow};
+use bevy_mod_picking::backend::prelude::Pickable;
 use rand::Rng;
 
 use crate::{
@@ -8,16 +11,19 @@ use crate::{
         A_BORDER_BOTTOM, A_BORDER_LEFT, A_BORDER_RIGHT, A_BORDER_TOP, A_CORNER_BL, A_CORNER_BR, A_CORNER_TL,
         A_CORNER_TR, A_DARK_GROUND,
     },
-    expedition::{ExpeditionPersist, ExpeditionStatus, InitExpedition},
-    point::{xy_to_idx, UPoint},
+    expedition::{mining_input_allowed, ExpeditionPersist, ExpeditionPhase, InitExpedition},
+    point::{tile_to_world, world_to_tile, Grid, IPoint, UPoint},
+    save::Progress,
     stability::StabilityDamage,
-    tools::{ActiveTool, PickaxeRotation, ToolType},
-    treasures::CheckTreasure,
-    AppState, SPRITE_PX_X, SPRITE_PX_Y,
+    tools::{ActiveTool, PickaxeRotation, ToolType, ToolUpgrades},
+    treasures::{CheckTreasure, Treasure},
+    AppState, SystemOrder, SPRITE_PX_X, SPRITE_PX_Y,
 };
 
 const BREAKABLE_Z: f32 = 30.0;
 const BACKGROUND_Z: f32 = 1.0;
+const HIGHLIGHT_Z: f32 = 35.0;
+const HIGHLIGHT_COLOR: Color = Color::rgba(1.0, 1.0, 0.8, 0.3);
 
 pub struct MiningPlugin;
 
@@ -27,26 +33,34 @@ impl Plugin for MiningPlugin {
             .add_systems(
                 Update,
                 (
-                    player_mouse_mine.before(handle_mine_actions),
-                    handle_mine_actions,
+                    player_mouse_mine.before(handle_mine_actions).run_if(mining_input_allowed),
+                    // cant mine after the expedition is finished
+                    handle_mine_actions.run_if(in_state(ExpeditionPhase::Mining)),
                     update_mining_tile.after(handle_mine_actions),
+                    preview_tool_footprint.after(handle_mine_actions).in_set(SystemOrder::Logic),
+                    update_footprint_highlights
+                        .run_if(resource_changed::<FootprintPreview>())
+                        .in_set(SystemOrder::Render)
+                        .after(SystemOrder::Logic),
                 )
                     .run_if(in_state(AppState::Expedition)),
             )
-            .add_event::<MineAction>();
+            .add_systems(OnExit(AppState::Expedition), clear_footprint_preview)
+            .init_resource::<FootprintPreview>()
+            .add_event::<MineAction>()
+            .add_event::<TileBroken>()
+            .add_event::<MineResult>();
     }
 }
 
 #[derive(Component)]
 pub struct MiningGrid {
-    pub rock_tiles: Vec<Option<Entity>>,
-    pub width: usize,
-    pub height: usize,
+    pub rock_tiles: Grid<Option<Entity>>,
 }
 
 impl MiningGrid {
     fn new(width: usize, height: usize) -> Self {
-        Self { rock_tiles: vec![None; width * height], width, height }
+        Self { rock_tiles: Grid::new(width, height, None) }
     }
 }
 
@@ -62,13 +76,56 @@ impl MiningTile {
 }
 struct TileHit {
     tile: Entity,
+    pos: UPoint,
     damage: usize,
 }
 
 #[derive(Event)]
 pub struct MineAction {
-    tile_x: u32,
-    tile_y: u32,
+    tile: UPoint,
+}
+
+/// What a mining action ended up doing, sent once for every click on or around the grid
+#[derive(Event, Clone, Copy, Debug)]
+pub struct MineResult(pub MineOutcome);
+
+/// Ordered from least to most notable so the most notable thing a swing did can be picked with `max`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MineOutcome {
+    /// Every tile hit was already broken
+    Empty,
+    /// The border around the grid was hit
+    Bedrock,
+    /// Rocks were hit but none of them broke, `hp_left` is the most any of them has left
+    Rock {
+        hp_left: usize,
+    },
+    RockBroken,
+    /// A broken rock had part of an undiscovered treasure under it
+    TreasureExposed,
+}
+
+/// Sent when a tile's hp reaches 0, `pos` is where the tile was in the world
+#[derive(Event)]
+pub struct TileBroken {
+    pub pos: Vec2,
+}
+
+/// Tiles the active tool would hit if the player clicked now, and the stability it would cost
+#[derive(Resource, Default, PartialEq)]
+pub struct FootprintPreview {
+    pub tiles: Vec<UPoint>,
+    /// `None` when the click would not hit anything
+    pub cost: Option<u32>,
+    /// Position of the cursor in the window
+    pub cursor: Option<Vec2>,
+}
+
+#[derive(Component)]
+struct FootprintHighlight;
+
+fn clear_footprint_preview(mut preview: ResMut<FootprintPreview>) {
+    *preview = FootprintPreview::default();
 }
 
 fn init_mining_grid(mut commands: Commands, mut ev_init: EventReader<InitExpedition>, sprites: Res<SpriteAssets>) {
@@ -79,61 +136,57 @@ fn init_mining_grid(mut commands: Commands, mut ev_init: EventReader<InitExpedit
     info!("running init mining grid {:?}", new_grid);
 
     let mut grid = MiningGrid::new(new_grid.size_x, new_grid.size_y);
-    let mut rng = rand::thread_rng();
-
-    for y in 0..grid.height {
-        for x in 0..grid.width {
-            let tile_idx = xy_to_idx(x, y, grid.width) as usize;
-            let x = (x * SPRITE_PX_X as usize) as f32;
-            let y = (y * SPRITE_PX_Y as usize) as f32;
-            let hp: usize = rng.gen::<usize>() % 4;
-            let tile = commands.spawn((
-                MiningTile::new(hp + 1),
-                SpriteSheetBundle {
-                    texture_atlas: sprites.lvl1xped.clone(),
-                    sprite: TextureAtlasSprite::new(hp),
-                    transform: Transform::from_xyz(x, y, BREAKABLE_Z),
-                    ..default()
-                },
-                ExpeditionPersist,
-            ));
-            grid.rock_tiles[tile_idx] = Some(tile.id());
+    let mut rng = new_grid.rng(0);
 
-            let _bg = commands.spawn((
-                SpriteSheetBundle {
-                    texture_atlas: sprites.lvl1xped.clone(),
-                    sprite: TextureAtlasSprite::new(5),
-                    transform: Transform::from_xyz(x, y, BACKGROUND_Z),
-                    ..default()
-                },
-                ExpeditionPersist,
-            ));
-        }
-    }
+    let positions = grid.rock_tiles.iter().map(|(pos, _)| pos).collect::<Vec<_>>();
+    for pos in positions {
+        let world = tile_to_world(pos.into());
+        let hp: usize = rng.gen::<usize>() % 4;
+        let tile = commands.spawn((
+            MiningTile::new(hp + 1),
+            SpriteSheetBundle {
+                texture_atlas: sprites.lvl1xped.clone(),
+                sprite: TextureAtlasSprite::new(hp),
+                transform: Transform::from_translation(world.extend(BREAKABLE_Z)),
+                ..default()
+            },
+            ExpeditionPersist,
+        ));
+        grid.rock_tiles[pos] = Some(tile.id());
 
-    commands.spawn((grid, ExpeditionPersist));
-    info!("created mining grid");
+        let _bg = commands.spawn((
+            SpriteSheetBundle {
+                texture_atlas: sprites.lvl1xped.clone(),
+                sprite: TextureAtlasSprite::new(5),
+                transform: Transform::from_translation(world.extend(BACKGROUND_Z)),
+                ..default()
+            },
+            ExpeditionPersist,
+        ));
+    }
 
     for y in (-50)..50 {
         for x in (-50)..50 {
-            if (0..new_grid.size_x as i32).contains(&x) && (0..new_grid.size_y as i32).contains(&y) {
+            let pos = IPoint::new(x, y);
+            if grid.rock_tiles.checked(pos).is_some() {
                 continue;
             }
-            let atlas_idx = get_border_atlas_idx(x, y, (new_grid.size_x as i32, new_grid.size_y as i32));
+            let atlas_idx = get_border_atlas_idx(pos, &grid.rock_tiles);
 
-            let x = (x * SPRITE_PX_X as i32) as f32;
-            let y = (y * SPRITE_PX_Y as i32) as f32;
             commands.spawn((
                 SpriteSheetBundle {
                     texture_atlas: sprites.lvl1xped.clone(),
                     sprite: TextureAtlasSprite::new(atlas_idx),
-                    transform: Transform::from_xyz(x, y, BACKGROUND_Z),
+                    transform: Transform::from_translation(tile_to_world(pos).extend(BACKGROUND_Z)),
                     ..default()
                 },
                 ExpeditionPersist,
             ));
         }
     }
+
+    commands.spawn((grid, ExpeditionPersist));
+    info!("created mining grid");
 }
 
 /// Mouse Input for player to touch the mining tiles
@@ -142,6 +195,7 @@ fn player_mouse_mine(
     q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
     q_mining_grid: Query<&MiningGrid>,
     mut ev_mine: EventWriter<MineAction>,
+    mut ev_result: EventWriter<MineResult>,
     mouse: Res<Input<MouseButton>>,
 ) {
     if !mouse.just_pressed(MouseButton::Left) {
@@ -150,54 +204,142 @@ fn player_mouse_mine(
     let (cam, cam_trans) = q_camera.single();
     let window = q_windows.single();
 
-    if let Some(world_pos) = window
-        .cursor_position()
-        .and_then(|cursor| cam.viewport_to_world(cam_trans, cursor))
-        .map(|ray| ray.origin.truncate())
-    {
-        let tile_x = (world_pos.x / SPRITE_PX_X as f32).round() as i32;
-        let tile_y = (world_pos.y / SPRITE_PX_Y as f32).round() as i32;
-        println!("World coords: {}/{} Tile coords: {}/{}", world_pos.x, world_pos.y, tile_x, tile_y);
+    if let Some(world_pos) = cursor_world_pos(window, cam, cam_trans) {
+        let tile_pos = world_to_tile(world_pos);
+        debug!("World coords: {}/{} Tile coords: {:?}", world_pos.x, world_pos.y, tile_pos);
 
         let grid = q_mining_grid.single();
         // Click needs to be within grid of mineable rocks to even be considered a mining action
-        if tile_x < 0 || tile_y < 0 || tile_x >= grid.width as i32 || tile_y >= grid.height as i32 {
+        let Some(tile) = grid.rock_tiles.checked(tile_pos) else {
             warn!("Click was outside the mining grid.");
+            if is_border(tile_pos, &grid.rock_tiles) {
+                ev_result.send(MineResult(MineOutcome::Bedrock));
+            }
             return;
-        }
+        };
 
-        ev_mine.send(MineAction { tile_x: tile_x as u32, tile_y: tile_y as u32 });
+        ev_mine.send(MineAction { tile });
     }
 }
 
-fn handle_mine_actions(
-    mut ev_mine: EventReader<MineAction>,
-    mut q_mining_tiles: Query<&mut MiningTile>,
-    mut ev_stability: EventWriter<StabilityDamage>,
-    mut ev_treasure_check: EventWriter<CheckTreasure>,
+/// Where the cursor is in the world as seen by the main camera, `None` when the cursor is outside the window
+fn cursor_world_pos(window: &Window, cam: &Camera, cam_trans: &GlobalTransform) -> Option<Vec2> {
+    window
+        .cursor_position()
+        .and_then(|cursor| cam.viewport_to_world(cam_trans, cursor))
+        .map(|ray| ray.origin.truncate())
+}
+
+/// Works out what the active tool would hit if the player clicked right now
+fn preview_tool_footprint(
+    q_windows: Query<&Window, With<PrimaryWindow>>,
+    q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
     q_mining_grid: Query<&MiningGrid>,
     tool: Res<ActiveTool>,
-    expedition_status: Res<ExpeditionStatus>,
+    progress: Res<Progress>,
+    phase: Res<State<ExpeditionPhase>>,
+    mut preview: ResMut<FootprintPreview>,
 ) {
-    match *expedition_status {
-        ExpeditionStatus::Cleared | ExpeditionStatus::Leaving => {
-            return; // cant mine after the expedition is finished
+    let (cam, cam_trans) = q_camera.single();
+    let window = q_windows.single();
+    let cursor = window.cursor_position();
+    let hovered = match (q_mining_grid.get_single(), cursor_world_pos(window, cam, cam_trans)) {
+        (Ok(grid), Some(world_pos)) if *phase.get() == ExpeditionPhase::Mining => {
+            grid.rock_tiles.checked(world_to_tile(world_pos)).map(|tile| (grid, tile))
+        }
+        _ => None,
+    };
+    let Some((grid, tile)) = hovered else {
+        preview.set_if_neq(FootprintPreview::default());
+        return;
+    };
+
+    let tiles_hit = get_tile_hits(&tool.0, &tile, grid, &progress.upgrades);
+    let cost = (!tiles_hit.is_empty()).then(|| get_hit_stability(&tool.0, &tiles_hit, &progress.upgrades));
+    let mut tiles = get_hit_spots(&tool.0, &tile, grid, &progress.upgrades).into_iter().collect::<Vec<_>>();
+    // keeps the preview from looking changed every frame just because the set came out in a different order
+    tiles.sort_by_key(|pos| (pos.y, pos.x));
+    preview.set_if_neq(FootprintPreview { tiles, cost, cursor });
+}
+
+fn update_footprint_highlights(
+    mut commands: Commands,
+    preview: Res<FootprintPreview>,
+    mut q_highlights: Query<(&mut Transform, &mut Visibility), With<FootprintHighlight>>,
+) {
+    let mut tiles = preview.tiles.iter();
+    for (mut transform, mut vis) in q_highlights.iter_mut() {
+        match tiles.next() {
+            Some(tile) => {
+                transform.translation = tile_to_world(IPoint::from(*tile)).extend(HIGHLIGHT_Z);
+                *vis = Visibility::Visible;
+            }
+            None => *vis = Visibility::Hidden,
         }
-        ExpeditionStatus::Mining => {}
     }
 
+    // not enough highlights have been made yet for this footprint
+    for tile in tiles {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: HIGHLIGHT_COLOR,
+                    custom_size: Some(Vec2::new(SPRITE_PX_X as f32, SPRITE_PX_Y as f32)),
+                    ..default()
+                },
+                transform: Transform::from_translation(tile_to_world(IPoint::from(*tile)).extend(HIGHLIGHT_Z)),
+                ..default()
+            },
+            Pickable::IGNORE,
+            FootprintHighlight,
+            ExpeditionPersist,
+        ));
+    }
+}
+
+/// Everything that gets told about a swing landing
+#[derive(SystemParam)]
+struct MineEvents<'w> {
+    stability: EventWriter<'w, StabilityDamage>,
+    treasure_check: EventWriter<'w, CheckTreasure>,
+    broken: EventWriter<'w, TileBroken>,
+    result: EventWriter<'w, MineResult>,
+}
+
+fn handle_mine_actions(
+    mut ev_mine: EventReader<MineAction>,
+    mut q_mining_tiles: Query<(&mut MiningTile, &Transform)>,
+    mut events: MineEvents,
+    q_mining_grid: Query<&MiningGrid>,
+    q_treasures: Query<&Treasure>,
+    tool: Res<ActiveTool>,
+    progress: Res<Progress>,
+) {
     let grid = q_mining_grid.single();
     for ev in ev_mine.read() {
-        let start = UPoint::new(ev.tile_x as usize, ev.tile_y as usize);
-        let tiles_hit = get_tile_hits(&tool.0, &start, &grid);
-        if tiles_hit.len() != 0 {
-            ev_stability.send(StabilityDamage::new(get_hit_stability(&tool.0, &tiles_hit)));
+        let tiles_hit = get_tile_hits(&tool.0, &ev.tile, grid, &progress.upgrades);
+        if !tiles_hit.is_empty() {
+            events.stability.send(StabilityDamage::new(get_hit_stability(&tool.0, &tiles_hit, &progress.upgrades)));
         }
-        for TileHit { tile, damage } in tiles_hit.iter() {
+        let mut outcome = MineOutcome::Empty;
+        for TileHit { tile, pos, damage } in tiles_hit.iter() {
             match q_mining_tiles.get_mut(*tile) {
-                Ok(mut hit) => {
+                Ok((mut hit, transform)) => {
+                    let was_standing = hit.hp > 0;
                     hit.hp = hit.hp.saturating_sub(*damage);
-                    ev_treasure_check.send(CheckTreasure {});
+                    if was_standing && hit.hp == 0 {
+                        events.broken.send(TileBroken { pos: transform.translation.truncate() });
+                        let exposes_treasure =
+                            q_treasures.iter().any(|treasure| !treasure.is_discovered && treasure.parts.contains(pos));
+                        outcome = outcome.max(if exposes_treasure {
+                            MineOutcome::TreasureExposed
+                        } else {
+                            MineOutcome::RockBroken
+                        });
+                    } else if hit.hp > 0 {
+                        outcome = outcome.max(MineOutcome::Rock { hp_left: hit.hp });
+                    }
+                    events.treasure_check.send(CheckTreasure {});
                     debug!("Tile was hit");
                 }
                 Err(_) => {
@@ -206,6 +348,7 @@ fn handle_mine_actions(
                 }
             };
         }
+        events.result.send(MineResult(outcome));
     }
 }
 
@@ -219,100 +362,80 @@ fn update_mining_tile(mut q_mining_tiles: Query<(&mut Visibility, &mut TextureAt
     }
 }
 
+/// Helper: if the position is in the border right around the grid
+fn is_border<T>(pos: IPoint, grid: &Grid<T>) -> bool {
+    let (right, top) = (grid.width() as i32, grid.height() as i32);
+    grid.checked(pos).is_none() && (-1..=right).contains(&pos.x) && (-1..=top).contains(&pos.y)
+}
+
 /// Helper: to find which atlas index to use for creating the border around the mineable tiles
-fn get_border_atlas_idx(x: i32, y: i32, grid: (i32, i32)) -> usize {
-    if x < -1 || y < -1 || x > grid.0 || y > grid.1 {
+fn get_border_atlas_idx<T>(pos: IPoint, grid: &Grid<T>) -> usize {
+    let (right, top) = (grid.width() as i32, grid.height() as i32);
+    if pos.x < -1 || pos.y < -1 || pos.x > right || pos.y > top {
         A_DARK_GROUND
-    } else if x == -1 {
-        if y == -1 {
+    } else if pos.x == -1 {
+        if pos.y == -1 {
             A_CORNER_BL
-        } else if y == grid.1 {
+        } else if pos.y == top {
             A_CORNER_TL
         } else {
             A_BORDER_LEFT
         }
-    } else if x == grid.0 {
-        if y == -1 {
+    } else if pos.x == right {
+        if pos.y == -1 {
             A_CORNER_BR
-        } else if y == grid.1 {
+        } else if pos.y == top {
             A_CORNER_TR
         } else {
             A_BORDER_RIGHT
         }
-    } else if y == -1 {
+    } else if pos.y == -1 {
         A_BORDER_BOTTOM
-    } else if y == grid.1 {
+    } else if pos.y == top {
         A_BORDER_TOP
     } else {
         A_DARK_GROUND
     }
 }
 
-fn get_hit_stability(tool: &ToolType, _hits: &[TileHit]) -> u32 {
-    match tool {
+fn get_hit_stability(tool: &ToolType, _hits: &[TileHit], upgrades: &ToolUpgrades) -> u32 {
+    let base = match tool {
         ToolType::TinyHammer => 75,
         ToolType::Pickaxe { rotation } => match rotation {
             PickaxeRotation::Horizontal => 25,
             PickaxeRotation::Vertical => 25,
             PickaxeRotation::Cross => 45,
         },
-    }
+    };
+    upgrades.stability_cost(base)
 }
 
-// Helper: returns entity and how much damage dealt based on which tool is used
-fn get_tile_hits(tool: &ToolType, start_pos: &UPoint, grid: &MiningGrid) -> Vec<TileHit> {
-    match tool {
-        ToolType::TinyHammer => {
-            let start_idx = start_pos.as_idx(grid.width);
-            if let Some(maybe_rock) = grid.rock_tiles.get(start_idx) {
-                if let Some(rock) = maybe_rock {
-                    return vec![(TileHit { tile: *rock, damage: 1 })];
-                }
-            }
-            return vec![];
-        }
-        ToolType::Pickaxe { rotation } => {
-            let mut hits = vec![];
-            let hit_spots = match rotation {
-                PickaxeRotation::Horizontal => {
-                    vec![
-                        *start_pos,
-                        UPoint::new(start_pos.x.saturating_sub(1), start_pos.y),
-                        UPoint::new(start_pos.x + 1, start_pos.y),
-                    ]
-                }
-                PickaxeRotation::Vertical => {
-                    vec![
-                        *start_pos,
-                        UPoint::new(start_pos.x, start_pos.y.saturating_sub(1)),
-                        UPoint::new(start_pos.x, start_pos.y + 1),
-                    ]
-                }
-                PickaxeRotation::Cross => {
-                    vec![
-                        *start_pos,
-                        UPoint::new(start_pos.x, start_pos.y.saturating_sub(1)),
-                        UPoint::new(start_pos.x, start_pos.y + 1),
-                        UPoint::new(start_pos.x.saturating_sub(1), start_pos.y),
-                        UPoint::new(start_pos.x + 1, start_pos.y),
-                    ]
-                }
-            };
-            let hit_spots = hit_spots
-                .into_iter()
-                .filter(|spot| spot.x < grid.width && spot.y < grid.height)
-                .collect::<HashSet<_>>();
+/// Offsets from the clicked tile that the tool will hit, pickaxes hit `reach` tiles out along each arm
+fn get_tool_footprint(tool: &ToolType, reach: i32) -> Vec<IPoint> {
+    let arms: &[IPoint] = match tool {
+        ToolType::TinyHammer => &[],
+        ToolType::Pickaxe { rotation } => match rotation {
+            PickaxeRotation::Horizontal => &[IPoint::LEFT, IPoint::RIGHT],
+            PickaxeRotation::Vertical => &[IPoint::DOWN, IPoint::UP],
+            PickaxeRotation::Cross => &[IPoint::DOWN, IPoint::UP, IPoint::LEFT, IPoint::RIGHT],
+        },
+    };
+    once(IPoint::ZERO).chain(arms.iter().flat_map(|arm| (1..=reach).map(move |dist| *arm * dist))).collect()
+}
 
-            for spot in hit_spots {
-                let idx = spot.as_idx(grid.width);
+/// Every tile inside the grid that the tool will hit
+fn get_hit_spots(tool: &ToolType, start_pos: &UPoint, grid: &MiningGrid, upgrades: &ToolUpgrades) -> HashSet<UPoint> {
+    get_tool_footprint(tool, upgrades.reach())
+        .into_iter()
+        .filter_map(|offset| grid.rock_tiles.checked(IPoint::from(*start_pos) + offset))
+        .collect()
+}
 
-                if let Some(maybe_rock) = grid.rock_tiles.get(idx) {
-                    if let Some(rock) = maybe_rock {
-                        hits.push(TileHit { tile: *rock, damage: 1 });
-                    }
-                }
-            }
-            return hits;
-        }
-    }
+// Helper: returns entity and how much damage dealt based on which tool is used
+fn get_tile_hits(tool: &ToolType, start_pos: &UPoint, grid: &MiningGrid, upgrades: &ToolUpgrades) -> Vec<TileHit> {
+    get_hit_spots(tool, start_pos, grid, upgrades)
+        .into_iter()
+        .filter_map(|spot| grid.rock_tiles[spot].map(|rock| (rock, spot)))
+        .map(|(rock, pos)| TileHit { tile: rock, pos, damage: 1 + upgrades.extra_damage() })
+        .collect()
 }
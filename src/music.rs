@@ -0,0 +1,236 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_kira_audio::{AudioApp, AudioChannel, AudioControl, AudioInstance, AudioTween};
+use rand::{seq::SliceRandom, thread_rng, Rng};
+
+use crate::{
+    assets::SoundAssets,
+    data_read::{AmbientSound, AreaAudio, LevelDb, MusicLayer},
+    expedition::{ActiveLevel, Area},
+    settings::Settings,
+    stability::Stability,
+    AppState,
+};
+
+/// Seconds for the music of one area to fade into the next
+const CROSSFADE_SECS: f32 = 2.0;
+/// Seconds for an intensity layer to fade in or out
+const LAYER_FADE_SECS: f32 = 1.5;
+/// Seconds for the music to drop away when ducked
+const DUCK_ATTACK_SECS: f32 = 0.1;
+/// Seconds the music stays ducked before coming back up
+const DUCK_HOLD_SECS: f32 = 1.2;
+
+pub struct MusicPlugin;
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_audio_channel::<MusicChannel>()
+            .add_audio_channel::<AmbienceChannel>()
+            .init_resource::<Soundscape>()
+            .init_resource::<Ducking>()
+            .add_event::<DuckMusic>()
+            .add_systems(
+                Update,
+                (change_soundscape.run_if(state_changed::<AppState>()), duck_music, fade_music_layers, play_ambience)
+                    .chain()
+                    .run_if(not(in_state(AppState::AssetLoading))),
+            );
+    }
+}
+
+/// Channel the music plays on, kept apart from the sound effects so each can have its own volume
+#[derive(Resource)]
+pub struct MusicChannel;
+
+/// Channel the background sounds of an area play on
+#[derive(Resource)]
+pub struct AmbienceChannel;
+
+/// Turns the music down for a moment so a big sound can cut through, 1 silences it
+#[derive(Event)]
+pub struct DuckMusic(pub f32);
+
+/// How far down the music is ducked and for how much longer
+#[derive(Resource, Default)]
+struct Ducking {
+    amount: f32,
+    hold: f32,
+}
+
+/// What is currently playing for the area the player is in
+#[derive(Resource, Default)]
+struct Soundscape {
+    area: Option<Area>,
+    layers: Vec<PlayingLayer>,
+    ambience: Vec<AmbientTimer>,
+    /// Instances of the last area that still need to be faded out
+    fading_out: Vec<Handle<AudioInstance>>,
+}
+
+struct PlayingLayer {
+    layer: MusicLayer,
+    instance: Handle<AudioInstance>,
+    /// Volume the layer was last faded towards
+    target: f32,
+}
+
+struct AmbientTimer {
+    sound: AmbientSound,
+    /// Seconds left until the sound plays again
+    wait: f32,
+}
+
+impl AmbientTimer {
+    fn new(sound: AmbientSound, intensity: f32) -> Self {
+        let mut timer = Self { sound, wait: 0.0 };
+        timer.restart(intensity);
+        timer
+    }
+
+    fn restart(&mut self, intensity: f32) {
+        let (min, max) = self.sound.every;
+        let wait = thread_rng().gen_range(min.min(max)..=max.max(min));
+        self.wait = wait * (1.0 - self.sound.busier * intensity).max(0.1);
+    }
+}
+
+/// How tense the music should be, rises from 0 to 1 as the stability of an expedition runs out
+fn intensity(app_state: &AppState, stability: Option<&Stability>) -> f32 {
+    match (app_state, stability) {
+        (AppState::Expedition, Some(stability)) => (1.0 - stability.fraction()).clamp(0.0, 1.0),
+        _ => 0.0,
+    }
+}
+
+fn current_area(app_state: &AppState, active_level: &ActiveLevel) -> Option<Area> {
+    match app_state {
+        AppState::AssetLoading => None,
+        AppState::AreaViewer { curr_area } | AppState::Collection { curr_area } | AppState::Shop { curr_area } => {
+            Some(curr_area.clone())
+        }
+        AppState::Expedition | AppState::LevelTransition => Some(active_level.area.clone()),
+    }
+}
+
+/// Crossfades to the music and ambience of the new area whenever a state change moves the player to one
+fn change_soundscape(
+    mut soundscape: ResMut<Soundscape>,
+    app_state: Res<State<AppState>>,
+    active_level: Res<ActiveLevel>,
+    level_db: Res<LevelDb>,
+    asset_server: Res<AssetServer>,
+    music: Res<AudioChannel<MusicChannel>>,
+) {
+    let area = current_area(app_state.get(), &active_level);
+    if area == soundscape.area {
+        return;
+    }
+    debug!("changing soundscape to {:?}", area);
+
+    let old_layers = std::mem::take(&mut soundscape.layers);
+    soundscape.fading_out.extend(old_layers.into_iter().map(|playing| playing.instance));
+    soundscape.ambience.clear();
+
+    let audio = area
+        .as_ref()
+        .and_then(|area| level_db.get(&area.to_string()))
+        .map(|info| info.audio.clone())
+        .unwrap_or_default();
+    let AreaAudio { music: layers, ambience } = audio;
+    // every layer starts together and silent so they stay in time when fading in later
+    soundscape.layers = layers
+        .into_iter()
+        .map(|layer| {
+            let instance = music.play(asset_server.load(&layer.path)).looped().with_volume(0.0).handle();
+            PlayingLayer { layer, instance, target: 0.0 }
+        })
+        .collect();
+    soundscape.ambience = ambience.into_iter().map(|sound| AmbientTimer::new(sound, 0.0)).collect();
+    soundscape.area = area;
+}
+
+fn duck_music(mut ev_duck: EventReader<DuckMusic>, mut ducking: ResMut<Ducking>, time: Res<Time>) {
+    for DuckMusic(amount) in ev_duck.read() {
+        ducking.amount = ducking.amount.max(amount.clamp(0.0, 1.0));
+        ducking.hold = DUCK_HOLD_SECS;
+    }
+    ducking.hold -= time.delta_seconds();
+    if ducking.hold <= 0.0 {
+        ducking.amount = 0.0;
+    }
+}
+
+/// Fades each layer in or out for the current intensity, also picks up changes to the music volume
+fn fade_music_layers(
+    mut soundscape: ResMut<Soundscape>,
+    mut instances: ResMut<Assets<AudioInstance>>,
+    app_state: Res<State<AppState>>,
+    stability: Option<Res<Stability>>,
+    settings: Res<Settings>,
+    ducking: Res<Ducking>,
+) {
+    // an instance only exists once its track has loaded so stopping is retried until then
+    soundscape.fading_out.retain(|handle| match instances.get_mut(handle) {
+        Some(instance) => {
+            instance.stop(AudioTween::linear(Duration::from_secs_f32(CROSSFADE_SECS)));
+            false
+        }
+        None => true,
+    });
+
+    let intensity = intensity(app_state.get(), stability.as_deref());
+    let music_volume = settings.master_volume * settings.music_volume * (1.0 - ducking.amount);
+    for playing in soundscape.layers.iter_mut() {
+        let target = match intensity >= playing.layer.intensity {
+            true => playing.layer.volume * music_volume,
+            false => 0.0,
+        };
+        if target == playing.target {
+            continue;
+        }
+        let Some(instance) = instances.get_mut(&playing.instance) else {
+            continue;
+        };
+        let fade = if ducking.amount > 0.0 && target < playing.target {
+            DUCK_ATTACK_SECS
+        } else if playing.target == 0.0 && playing.layer.intensity == 0.0 {
+            CROSSFADE_SECS
+        } else {
+            LAYER_FADE_SECS
+        };
+        instance.set_volume(target as f64, AudioTween::linear(Duration::from_secs_f32(fade)));
+        playing.target = target;
+    }
+}
+
+/// Plays each ambient sound of the area at random times, more often the higher the intensity
+fn play_ambience(
+    mut soundscape: ResMut<Soundscape>,
+    app_state: Res<State<AppState>>,
+    stability: Option<Res<Stability>>,
+    time: Res<Time>,
+    sounds: Res<SoundAssets>,
+    ambience: Res<AudioChannel<AmbienceChannel>>,
+) {
+    let intensity = intensity(app_state.get(), stability.as_deref());
+    let mut rng = thread_rng();
+    for timer in soundscape.ambience.iter_mut() {
+        if intensity < timer.sound.intensity {
+            continue;
+        }
+        timer.wait -= time.delta_seconds();
+        if timer.wait > 0.0 {
+            continue;
+        }
+        timer.restart(intensity);
+        let Some(clip) = timer.sound.clips.choose(&mut rng) else {
+            continue;
+        };
+        let mut pick = |(min, max): (f32, f32)| rng.gen_range(min.min(max)..=max.max(min));
+        let volume = pick(timer.sound.volume);
+        let pitch = pick(timer.sound.pitch);
+        ambience.play(sounds.clip(*clip)).with_volume(volume as f64).with_playback_rate(pitch.max(0.05) as f64);
+    }
+}
@@ -1,3 +1,11 @@
+use std::ops::{Add, Index, IndexMut, Mul, Neg, Sub};
+
+use bevy::math::Vec2;
+use serde::Deserialize;
+
+use crate::{SPRITE_PX_X, SPRITE_PX_Y};
+
+/// A position on a [`Grid`]
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct UPoint {
     pub x: usize,
@@ -9,20 +17,236 @@ impl UPoint {
         Self { x, y }
     }
 
-    pub(crate) fn as_idx(&self, width: usize) -> usize {
+    pub fn as_idx(&self, width: usize) -> usize {
         xy_to_idx(self.x, self.y, width)
     }
 }
 
+/// A signed position or an offset from a position
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub struct IPoint {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl IPoint {
+    pub const ZERO: IPoint = IPoint::new(0, 0);
+    pub const UP: IPoint = IPoint::new(0, 1);
+    pub const DOWN: IPoint = IPoint::new(0, -1);
+    pub const LEFT: IPoint = IPoint::new(-1, 0);
+    pub const RIGHT: IPoint = IPoint::new(1, 0);
+    pub const CARDINALS: [IPoint; 4] = [IPoint::UP, IPoint::RIGHT, IPoint::DOWN, IPoint::LEFT];
+
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+}
+
+impl Add for IPoint {
+    type Output = IPoint;
+
+    fn add(self, rhs: IPoint) -> IPoint {
+        IPoint::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl Sub for IPoint {
+    type Output = IPoint;
+
+    fn sub(self, rhs: IPoint) -> IPoint {
+        IPoint::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl Mul<i32> for IPoint {
+    type Output = IPoint;
+
+    fn mul(self, rhs: i32) -> IPoint {
+        IPoint::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+impl Neg for IPoint {
+    type Output = IPoint;
+
+    fn neg(self) -> IPoint {
+        IPoint::new(-self.x, -self.y)
+    }
+}
+
+impl From<UPoint> for IPoint {
+    fn from(value: UPoint) -> Self {
+        IPoint::new(value.x as i32, value.y as i32)
+    }
+}
+
+impl TryFrom<IPoint> for UPoint {
+    type Error = IPoint;
+
+    fn try_from(value: IPoint) -> Result<Self, IPoint> {
+        if value.x < 0 || value.y < 0 {
+            return Err(value);
+        }
+        Ok(UPoint::new(value.x as usize, value.y as usize))
+    }
+}
+
 pub fn idx_to_xy(idx: usize, width: usize) -> UPoint {
-    UPoint { x: idx / width, y: idx % width }
+    UPoint { x: idx % width, y: idx / width }
 }
 
 pub fn xy_to_idx(x: usize, y: usize, width: usize) -> usize {
     x + y * width
 }
 
-pub struct _IPoint {
-    pub x: i32,
-    pub y: i32,
+/// World position of the center of a tile, tiles are drawn centered on their position
+pub fn tile_to_world(pos: IPoint) -> Vec2 {
+    Vec2::new((pos.x * SPRITE_PX_X as i32) as f32, (pos.y * SPRITE_PX_Y as i32) as f32)
+}
+
+/// Tile that contains the world position, may be outside of any grid
+pub fn world_to_tile(pos: Vec2) -> IPoint {
+    IPoint::new((pos.x / SPRITE_PX_X as f32).round() as i32, (pos.y / SPRITE_PX_Y as f32).round() as i32)
+}
+
+/// Quarter turns to apply to a grid
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default, Deserialize)]
+pub enum Rotation {
+    #[default]
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+impl Rotation {
+    pub const ALL: [Rotation; 4] = [Rotation::None, Rotation::Cw90, Rotation::Cw180, Rotation::Cw270];
+
+    /// The rotation that undoes this one
+    pub fn inverse(self) -> Rotation {
+        match self {
+            Rotation::None => Rotation::None,
+            Rotation::Cw90 => Rotation::Cw270,
+            Rotation::Cw180 => Rotation::Cw180,
+            Rotation::Cw270 => Rotation::Cw90,
+        }
+    }
+
+    /// Angle to turn a sprite by in radians, negative since a positive angle turns counter clockwise on screen
+    pub fn radians(self) -> f32 {
+        let quarter_turns = match self {
+            Rotation::None => 0.0,
+            Rotation::Cw90 => 1.0,
+            Rotation::Cw180 => 2.0,
+            Rotation::Cw270 => 3.0,
+        };
+        -quarter_turns * std::f32::consts::FRAC_PI_2
+    }
+}
+
+/// A fixed size 2d grid of cells, stored row by row starting from (0, 0).
+/// (0, 0) is the bottom left cell and y goes up, the same way as world positions from [`tile_to_world`],
+/// so turns and flips are as seen on screen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid<T> {
+    cells: Vec<T>,
+    width: usize,
+    height: usize,
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn new(width: usize, height: usize, fill: T) -> Self {
+        Self { cells: vec![fill; width * height], width, height }
+    }
+
+    /// Turns the grid clockwise
+    pub fn rotated(&self, rotation: Rotation) -> Grid<T> {
+        match rotation {
+            Rotation::None => self.clone(),
+            Rotation::Cw180 => Grid { cells: self.cells.iter().rev().cloned().collect(), ..*self },
+            Rotation::Cw90 => {
+                Grid::from_fn(self.height, self.width, |pos| self[UPoint::new(self.width - 1 - pos.y, pos.x)].clone())
+            }
+            Rotation::Cw270 => {
+                Grid::from_fn(self.height, self.width, |pos| self[UPoint::new(pos.y, self.height - 1 - pos.x)].clone())
+            }
+        }
+    }
+
+    /// Mirrors the grid left to right
+    pub fn flipped_x(&self) -> Grid<T> {
+        Grid::from_fn(self.width, self.height, |pos| self[UPoint::new(self.width - 1 - pos.x, pos.y)].clone())
+    }
+
+    /// Mirrors the grid top to bottom
+    pub fn flipped_y(&self) -> Grid<T> {
+        Grid::from_fn(self.width, self.height, |pos| self[UPoint::new(pos.x, self.height - 1 - pos.y)].clone())
+    }
+}
+
+impl<T> Grid<T> {
+    /// Builds a grid from cells laid out row by row, `None` when there are not exactly `width * height` cells
+    pub fn from_vec(width: usize, height: usize, cells: Vec<T>) -> Option<Self> {
+        if cells.len() != width * height {
+            return None;
+        }
+        Some(Self { cells, width, height })
+    }
+
+    pub fn from_fn(width: usize, height: usize, mut f: impl FnMut(UPoint) -> T) -> Self {
+        let cells = (0..width * height).map(|idx| f(idx_to_xy(idx, width))).collect();
+        Self { cells, width, height }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Converts the point to a position on this grid if it is inside of it
+    pub fn checked(&self, pos: IPoint) -> Option<UPoint> {
+        UPoint::try_from(pos).ok().filter(|pos| pos.x < self.width && pos.y < self.height)
+    }
+
+    pub fn get(&self, pos: UPoint) -> Option<&T> {
+        if pos.x >= self.width {
+            return None;
+        }
+        self.cells.get(pos.as_idx(self.width))
+    }
+
+    pub fn get_mut(&mut self, pos: UPoint) -> Option<&mut T> {
+        if pos.x >= self.width {
+            return None;
+        }
+        self.cells.get_mut(pos.as_idx(self.width))
+    }
+
+    /// Every cell along with its position, row by row
+    pub fn iter(&self) -> impl Iterator<Item = (UPoint, &T)> {
+        self.cells.iter().enumerate().map(|(idx, cell)| (idx_to_xy(idx, self.width), cell))
+    }
+
+    /// The cells directly above, right, below and left of `pos` that are inside the grid
+    pub fn neighbours(&self, pos: UPoint) -> impl Iterator<Item = UPoint> + '_ {
+        IPoint::CARDINALS.into_iter().filter_map(move |offset| self.checked(IPoint::from(pos) + offset))
+    }
+}
+
+impl<T> Index<UPoint> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, pos: UPoint) -> &T {
+        self.get(pos).unwrap_or_else(|| panic!("{:?} is outside of a {}x{} grid", pos, self.width, self.height))
+    }
+}
+
+impl<T> IndexMut<UPoint> for Grid<T> {
+    fn index_mut(&mut self, pos: UPoint) -> &mut T {
+        let (width, height) = (self.width, self.height);
+        self.get_mut(pos).unwrap_or_else(|| panic!("{:?} is outside of a {}x{} grid", pos, width, height))
+    }
 }
@@ -0,0 +1,186 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data_read::{LevelDb, Requirement, TreasureDb},
+    expedition::{ActiveLevel, Area, ExpeditionPhase},
+    save::Progress,
+    stability::Stability,
+    treasures::Treasure,
+};
+
+pub struct ProgressionPlugin;
+
+impl Plugin for ProgressionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(ExpeditionPhase::Cleared), record_level_clear);
+    }
+}
+
+pub const MAX_STARS: u32 = 3;
+
+/// How each level has gone so far, kept in the save
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct LevelRecords {
+    levels: Vec<LevelRecord>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LevelRecord {
+    pub area: String,
+    pub level: String,
+    pub clears: u32,
+    pub best_stars: u32,
+}
+
+impl LevelRecords {
+    pub fn get(&self, area: &str, level: &str) -> Option<&LevelRecord> {
+        self.levels.iter().find(|record| record.area == area && record.level == level)
+    }
+
+    pub fn is_cleared(&self, area: &str, level: &str) -> bool {
+        self.get(area, level).is_some_and(|record| record.clears > 0)
+    }
+
+    pub fn total_stars(&self) -> u32 {
+        self.levels.iter().map(|record| record.best_stars).sum()
+    }
+
+    pub fn record_clear(&mut self, area: &str, level: &str, stars: u32) {
+        match self.levels.iter_mut().find(|record| record.area == area && record.level == level) {
+            Some(record) => {
+                record.clears += 1;
+                record.best_stars = record.best_stars.max(stars);
+            }
+            None => self.levels.push(LevelRecord {
+                area: area.to_string(),
+                level: level.to_string(),
+                clears: 1,
+                best_stars: stars,
+            }),
+        }
+    }
+}
+
+/// Stars are earned by how much of the starting stability is left over
+pub fn stars_for_clear(stability: &Stability) -> u32 {
+    match stability.fraction() {
+        f if f >= 0.5 => 3,
+        f if f >= 0.25 => 2,
+        _ => 1,
+    }
+}
+
+/// Everything needed to check if a requirement has been met
+pub struct RequirementCtx<'a> {
+    pub progress: &'a Progress,
+    pub level_db: &'a LevelDb,
+    pub treasure_db: &'a TreasureDb,
+}
+
+impl<'a> RequirementCtx<'a> {
+    /// Whether the level and the area it is in have had every requirement met
+    pub fn is_level_unlocked(&self, area: &Area, level_idx: usize) -> bool {
+        self.unmet(area, None).is_empty() && self.unmet(area, Some(level_idx)).is_empty()
+    }
+
+    /// Requirements of the level, or of the area when `level_idx` is `None`, that are not met yet
+    pub fn unmet(&self, area: &Area, level_idx: Option<usize>) -> Vec<&'a Requirement> {
+        let Some(area_info) = self.level_db.get(&area.to_string()) else {
+            return vec![];
+        };
+        let requirements = match level_idx {
+            Some(idx) => area_info.levels.get(idx).map_or(&[][..], |level| &level.requires[..]),
+            None => &area_info.requires[..],
+        };
+        requirements.iter().filter(|req| !self.is_met(req, area, level_idx)).collect()
+    }
+
+    fn is_met(&self, req: &Requirement, area: &Area, level_idx: Option<usize>) -> bool {
+        match req {
+            Requirement::ClearPrevious => match self.previous_level(area, level_idx) {
+                Some((area, level)) => self.progress.levels.is_cleared(&area, &level),
+                None => true,
+            },
+            Requirement::ClearLevel { area, level } => self.progress.levels.is_cleared(area, level),
+            Requirement::Treasure(id) => self.progress.trove.get(*id).is_some(),
+            Requirement::Stars(stars) => self.progress.levels.total_stars() >= *stars,
+            Requirement::Tool(tool) => self.progress.tools.is_unlocked(tool),
+        }
+    }
+
+    /// Text shown to the player for what still needs to be done
+    pub fn describe(&self, req: &Requirement, area: &Area, level_idx: Option<usize>) -> String {
+        match req {
+            Requirement::ClearPrevious => match self.previous_level(area, level_idx) {
+                Some((_, level)) => format!("Clear {}", level),
+                None => "Nothing to clear".to_string(),
+            },
+            Requirement::ClearLevel { area, level } => format!("Clear {} in {}", level, area),
+            Requirement::Treasure(id) => match self.treasure_db.get_by_id(*id) {
+                Some(treasure) => format!("Find the {}", treasure.name),
+                None => format!("Find treasure {}", id),
+            },
+            Requirement::Stars(stars) => {
+                format!("Earn {} stars ({}/{})", stars, self.progress.levels.total_stars(), stars)
+            }
+            Requirement::Tool(tool) => format!("Own the {}", tool.name()),
+        }
+    }
+
+    /// Area and level name of the level before this one, `None` when there is nothing before it
+    fn previous_level(&self, area: &Area, level_idx: Option<usize>) -> Option<(String, String)> {
+        let (prev_area, prev_idx) = match level_idx {
+            Some(idx) if idx > 0 => (area.clone(), Some(idx - 1)),
+            Some(_) => return None,
+            None => {
+                let pos = Area::ALL.iter().position(|a| a == area)?;
+                (Area::ALL.get(pos.checked_sub(1)?)?.clone(), None)
+            }
+        };
+        let area_name = prev_area.to_string();
+        let levels = &self.level_db.get(&area_name)?.levels;
+        let level = match prev_idx {
+            Some(idx) => levels.get(idx)?,
+            None => levels.last()?,
+        };
+        Some((area_name, level.name.clone()))
+    }
+}
+
+/// Resources a system needs to build a [`RequirementCtx`]
+#[derive(SystemParam)]
+pub struct Requirements<'w> {
+    pub progress: Res<'w, Progress>,
+    pub level_db: Res<'w, LevelDb>,
+    pub treasure_db: Res<'w, TreasureDb>,
+}
+
+impl Requirements<'_> {
+    pub fn ctx(&self) -> RequirementCtx<'_> {
+        RequirementCtx { progress: &self.progress, level_db: &self.level_db, treasure_db: &self.treasure_db }
+    }
+}
+
+/// Saves the clear and the stars earned once every treasure in the level has been dug up
+fn record_level_clear(
+    q_treasures: Query<&Treasure>,
+    stability: Res<Stability>,
+    level_db: Res<LevelDb>,
+    active_level: Res<ActiveLevel>,
+    mut progress: ResMut<Progress>,
+) {
+    if q_treasures.iter().any(|treasure| !treasure.is_discovered) {
+        debug!("left the level without finding every treasure, not counting it as a clear");
+        return;
+    }
+    let area_name = active_level.area.to_string();
+    let Some(level) = level_db.get(&area_name).and_then(|area| area.levels.get(active_level.level_idx)) else {
+        return;
+    };
+
+    let stars = stars_for_clear(&stability);
+    info!("cleared {} with {} stars", level.name, stars);
+    progress.levels.record_clear(&area_name, &level.name, stars);
+}
@@ -0,0 +1,236 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data_read::{LevelDb, TreasureDb},
+    expedition::{ActiveLevel, ExpeditionPhase, PauseState},
+    mining::{MineAction, MiningTile},
+    progression::stars_for_clear,
+    save::Progress,
+    stability::Stability,
+    tools::{ActiveTool, ToolType},
+    treasures::Treasure,
+    AppState,
+};
+
+pub struct RunStatsPlugin;
+
+impl Plugin for RunStatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunStats>()
+            .add_systems(OnEnter(AppState::Expedition), start_run_stats)
+            .add_systems(
+                Update,
+                (count_mine_actions, count_time, track_objectives.after(count_mine_actions).after(count_time))
+                    .run_if(in_state(AppState::Expedition))
+                    .run_if(in_state(PauseState::Running))
+                    .run_if(in_state(ExpeditionPhase::Mining)),
+            )
+            .add_systems(OnEnter(ExpeditionPhase::Cleared), finish_run_stats)
+            .add_systems(OnEnter(ExpeditionPhase::Failed), finish_run_stats)
+            .add_systems(OnEnter(ExpeditionPhase::LeftEarly), finish_run_stats);
+    }
+}
+
+/// Everything that happened in the current, or most recent, expedition, kept in the save once it is over
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct RunStats {
+    pub area: String,
+    pub level: String,
+    pub seed: u64,
+    pub outcome: RunOutcome,
+    pub tools: Vec<ToolUse>,
+    pub tiles_broken: u32,
+    pub stability_start: i32,
+    pub stability_spent: u32,
+    pub stability_remaining: i32,
+    pub seconds: f32,
+    /// Ids of the treasures dug up
+    pub found: Vec<u32>,
+    /// Ids of the treasures left in the rock
+    pub missed: Vec<u32>,
+    pub stars: u32,
+    /// Coins the brought back treasures are worth at the shop
+    pub reward_value: u32,
+    pub objectives: Vec<ObjectiveProgress>,
+}
+
+/// An optional goal for a level, on top of finding every treasure
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub enum Objective {
+    /// Clear without spending more than this much stability
+    MaxStabilitySpent(u32),
+    /// Clear in this many swings or fewer
+    MaxSwings(u32),
+    /// Clear before this many seconds have passed
+    TimeLimit(f32),
+    /// Clear without swinging any other tool
+    OnlyTool(ToolType),
+}
+
+impl Objective {
+    /// What the player is asked to do, shown before the level and in the results
+    pub fn describe(&self) -> String {
+        match self {
+            Objective::MaxStabilitySpent(max) => format!("Clear with at most {} stability spent", max),
+            Objective::MaxSwings(max) => format!("Clear in {} swings or fewer", max),
+            Objective::TimeLimit(secs) => format!("Clear within {:.0} seconds", secs),
+            Objective::OnlyTool(tool) => format!("Use only the {}", tool.name()),
+        }
+    }
+
+    /// Once broken the objective can't be met anymore this run
+    pub fn is_broken(&self, stats: &RunStats, stability: &Stability) -> bool {
+        match self {
+            Objective::MaxStabilitySpent(max) => stability.start - stability.remaining > *max as i32,
+            Objective::MaxSwings(max) => stats.actions() > *max,
+            Objective::TimeLimit(secs) => stats.seconds > *secs,
+            Objective::OnlyTool(tool) => stats.tools.iter().any(|used| used.tool != *tool),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ObjectiveProgress {
+    pub objective: Objective,
+    pub state: ObjectiveState,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ObjectiveState {
+    /// Not broken yet, it is met if the level is cleared like this
+    #[default]
+    OnTrack,
+    Met,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RunOutcome {
+    #[default]
+    InProgress,
+    Cleared,
+    Failed,
+    LeftEarly,
+}
+
+/// How many times a tool was swung
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ToolUse {
+    pub tool: ToolType,
+    pub actions: u32,
+}
+
+impl RunStats {
+    pub fn actions(&self) -> u32 {
+        self.tools.iter().map(|tool| tool.actions).sum()
+    }
+
+    /// Works out the results from how the run ended, `found` and `missed` have to be filled in first
+    pub fn finish(&mut self, outcome: RunOutcome, stability: &Stability, tdb: &TreasureDb) {
+        self.outcome = outcome;
+        self.stability_remaining = stability.remaining;
+        self.stability_spent = (stability.start - stability.remaining).max(0) as u32;
+        // only a clear that found everything earns stars, the same as what gets recorded for the level
+        let full_clear = outcome == RunOutcome::Cleared && self.missed.is_empty();
+        self.stars = if full_clear { stars_for_clear(stability) } else { 0 };
+        // leaving early still keeps whatever was dug up, only a cave-in loses it all
+        self.reward_value = match outcome {
+            RunOutcome::Cleared | RunOutcome::LeftEarly => {
+                self.found.iter().filter_map(|id| tdb.get_by_id(*id)).map(|t| t.value).sum()
+            }
+            RunOutcome::Failed | RunOutcome::InProgress => 0,
+        };
+        // objectives only count when every treasure was found
+        let met = self
+            .objectives
+            .iter()
+            .map(|progress| {
+                full_clear
+                    && progress.state == ObjectiveState::OnTrack
+                    && !progress.objective.is_broken(self, stability)
+            })
+            .collect::<Vec<_>>();
+        for (progress, met) in self.objectives.iter_mut().zip(met) {
+            progress.state = if met { ObjectiveState::Met } else { ObjectiveState::Failed };
+        }
+    }
+}
+
+fn start_run_stats(
+    mut stats: ResMut<RunStats>,
+    active_level: Res<ActiveLevel>,
+    level_db: Res<LevelDb>,
+    stability: Res<Stability>,
+) {
+    let level = level_db.get(&active_level.area.to_string()).and_then(|area| area.levels.get(active_level.level_idx));
+    let objectives = level.map_or_else(Vec::new, |level| {
+        level
+            .objectives
+            .iter()
+            .map(|objective| ObjectiveProgress { objective: *objective, state: ObjectiveState::OnTrack })
+            .collect()
+    });
+    *stats = RunStats {
+        area: active_level.area.to_string(),
+        level: level.map_or_else(String::new, |level| level.name.clone()),
+        objectives,
+        seed: active_level.seed,
+        stability_start: stability.start,
+        stability_remaining: stability.remaining,
+        ..default()
+    };
+}
+
+fn count_mine_actions(mut ev_mine: EventReader<MineAction>, active_tool: Res<ActiveTool>, mut stats: ResMut<RunStats>) {
+    for _ in ev_mine.read() {
+        match stats.tools.iter_mut().find(|tool| tool.tool == active_tool.0) {
+            Some(tool) => tool.actions += 1,
+            None => stats.tools.push(ToolUse { tool: active_tool.0, actions: 1 }),
+        }
+    }
+}
+
+fn count_time(time: Res<Time>, mut stats: ResMut<RunStats>) {
+    stats.seconds += time.delta_seconds();
+}
+
+/// Marks objectives as failed as soon as they are broken so the player can see it while mining
+fn track_objectives(mut stats: ResMut<RunStats>, stability: Res<Stability>) {
+    let broken = stats
+        .objectives
+        .iter()
+        .map(|progress| progress.state == ObjectiveState::OnTrack && progress.objective.is_broken(&stats, &stability))
+        .collect::<Vec<_>>();
+    for (progress, broken) in stats.objectives.iter_mut().zip(broken) {
+        if broken {
+            info!("objective failed: {}", progress.objective.describe());
+            progress.state = ObjectiveState::Failed;
+        }
+    }
+}
+
+/// Fills in the results once the expedition is over and keeps a copy in the save
+fn finish_run_stats(
+    phase: Res<State<ExpeditionPhase>>,
+    q_treasures: Query<&Treasure>,
+    q_mining_tiles: Query<&MiningTile>,
+    stability: Res<Stability>,
+    tdb: Res<TreasureDb>,
+    mut stats: ResMut<RunStats>,
+    mut progress: ResMut<Progress>,
+) {
+    let outcome = match phase.get() {
+        ExpeditionPhase::Cleared => RunOutcome::Cleared,
+        ExpeditionPhase::Failed => RunOutcome::Failed,
+        ExpeditionPhase::LeftEarly => RunOutcome::LeftEarly,
+        _ => return,
+    };
+    stats.tiles_broken = q_mining_tiles.iter().filter(|tile| tile.hp == 0).count() as u32;
+    stats.found = q_treasures.iter().filter(|t| t.is_discovered).map(|t| t.id).collect();
+    stats.missed = q_treasures.iter().filter(|t| !t.is_discovered).map(|t| t.id).collect();
+    stats.finish(outcome, &stability, &tdb);
+    info!("{} finished as {:?} in {:.1}s with {} actions", stats.level, stats.outcome, stats.seconds, stats.actions());
+    progress.last_run = Some(stats.clone());
+}
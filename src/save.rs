@@ -0,0 +1,75 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    economy::Wallet,
+    progression::LevelRecords,
+    run_stats::RunStats,
+    tools::{ToolUnlocks, ToolUpgrades},
+    treasures::TreasureTrove,
+};
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Progress::load()).add_systems(Last, save_progress.run_if(progress_changed));
+    }
+}
+
+const SAVE_PATH: &str = "save/progress.json5";
+
+/// Everything about the player's progress that should be kept between sessions
+#[derive(Resource, Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct Progress {
+    /// Clears in a row without finding a treasure of the area's pity rarity, keyed by area name
+    pub pity_counters: HashMap<String, u32>,
+    pub trove: TreasureTrove,
+    pub wallet: Wallet,
+    pub tools: ToolUnlocks,
+    pub upgrades: ToolUpgrades,
+    pub levels: LevelRecords,
+    /// Results of the last expedition played
+    pub last_run: Option<RunStats>,
+}
+
+/// Reads a json5 file, falling back to the default when it is missing or can't be read
+pub(crate) fn load_json5<T: DeserializeOwned + Default>(path: &str) -> T {
+    let Ok(contents) = fs::read_to_string(path) else {
+        info!("nothing found at {}, starting fresh", path);
+        return T::default();
+    };
+    json5::from_str(&contents).unwrap_or_else(|e| {
+        error!("{} could not be read, starting fresh: {}", path, e);
+        T::default()
+    })
+}
+
+pub(crate) fn save_json5<T: Serialize>(path: &str, value: &T) -> Result<(), String> {
+    if let Some(dir) = Path::new(path).parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let contents = json5::to_string(value).map_err(|e| e.to_string())?;
+    fs::write(path, contents).map_err(|e| e.to_string())
+}
+
+impl Progress {
+    fn load() -> Self {
+        load_json5(SAVE_PATH)
+    }
+}
+
+/// Skips the frame the progress was loaded in since nothing new needs saving then
+fn progress_changed(progress: Res<Progress>) -> bool {
+    progress.is_changed() && !progress.is_added()
+}
+
+fn save_progress(progress: Res<Progress>) {
+    match save_json5(SAVE_PATH, &*progress) {
+        Ok(()) => debug!("saved progress"),
+        Err(e) => error!("could not save progress to {}: {}", SAVE_PATH, e),
+    }
+}
@@ -0,0 +1,495 @@
+use bevy::{
+    prelude::*,
+    window::{PresentMode, PrimaryWindow, WindowMode, WindowResolution},
+};
+use bevy_kira_audio::{AudioChannel, AudioControl};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    audio_events::{SfxChannel, UiChannel},
+    cli::LaunchArgs,
+    music::AmbienceChannel,
+    save::{load_json5, save_json5},
+};
+
+pub struct SettingsPlugin {
+    pub settings: Settings,
+}
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.settings.clone())
+            .add_state::<SettingsMenu>()
+            .init_resource::<Rebinding>()
+            .add_event::<SettingsChange>()
+            .add_systems(
+                Update,
+                (
+                    handle_settings_changes,
+                    capture_rebind.run_if(|rebinding: Res<Rebinding>| rebinding.0.is_some()),
+                    close_settings_on_key
+                        .run_if(in_state(SettingsMenu::Open))
+                        .run_if(|rebinding: Res<Rebinding>| rebinding.0.is_none()),
+                ),
+            )
+            .add_systems(
+                PostUpdate,
+                (apply_audio_settings, apply_window_settings).run_if(resource_changed::<Settings>()),
+            )
+            .add_systems(Last, save_settings.run_if(settings_changed));
+    }
+}
+
+const SETTINGS_PATH: &str = "save/settings.json5";
+/// Resolutions the settings screen cycles through
+const RESOLUTIONS: [(u32, u32); 4] = [(1280, 720), (1600, 900), (1920, 1080), (2560, 1440)];
+/// How much a volume changes with each press of a volume button
+const VOLUME_STEP: f32 = 0.1;
+
+/// Whether the settings screen is open, it sits over whatever else is going on
+#[derive(States, PartialEq, Eq, Hash, Clone, Copy, Debug, Default)]
+pub enum SettingsMenu {
+    #[default]
+    Closed,
+    Open,
+}
+
+/// Player options kept in a config file next to the save
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub master_volume: f32,
+    pub sfx_volume: f32,
+    pub music_volume: f32,
+    pub ambience_volume: f32,
+    pub ui_volume: f32,
+    pub window_mode: WindowModeSetting,
+    pub resolution: (u32, u32),
+    pub vsync: bool,
+    /// Turns off the screen shake and falling debris
+    pub reduced_motion: bool,
+    pub keybinds: Keybinds,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.0,
+            sfx_volume: 1.0,
+            music_volume: 0.8,
+            ambience_volume: 0.8,
+            ui_volume: 0.7,
+            window_mode: WindowModeSetting::Windowed,
+            resolution: RESOLUTIONS[0],
+            vsync: true,
+            reduced_motion: false,
+            keybinds: Keybinds::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Loads the settings from the config file, anything given on the command line takes priority
+    pub fn load(args: &LaunchArgs) -> Self {
+        load_json5::<Settings>(SETTINGS_PATH).with_args(args)
+    }
+
+    /// Overrides the settings with what was given on the command line
+    pub fn with_args(mut self, args: &LaunchArgs) -> Self {
+        if let Some(mode) = args.window_mode() {
+            self.window_mode = mode;
+        }
+        if let Some((width, height)) = args.resolution {
+            self.resolution = (width as u32, height as u32);
+        }
+        self.reduced_motion |= args.reduced_motion;
+        self
+    }
+
+    pub fn window(&self) -> Window {
+        Window {
+            mode: self.window_mode.into(),
+            resolution: WindowResolution::new(self.resolution.0 as f32, self.resolution.1 as f32),
+            present_mode: self.present_mode(),
+            title: "Underground Miner".to_string(),
+            resizable: true,
+            ..Default::default()
+        }
+    }
+
+    fn present_mode(&self) -> PresentMode {
+        if self.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        }
+    }
+
+    pub fn volume(&self, kind: VolumeKind) -> f32 {
+        match kind {
+            VolumeKind::Master => self.master_volume,
+            VolumeKind::Sfx => self.sfx_volume,
+            VolumeKind::Music => self.music_volume,
+            VolumeKind::Ambience => self.ambience_volume,
+            VolumeKind::Ui => self.ui_volume,
+        }
+    }
+
+    fn volume_mut(&mut self, kind: VolumeKind) -> &mut f32 {
+        match kind {
+            VolumeKind::Master => &mut self.master_volume,
+            VolumeKind::Sfx => &mut self.sfx_volume,
+            VolumeKind::Music => &mut self.music_volume,
+            VolumeKind::Ambience => &mut self.ambience_volume,
+            VolumeKind::Ui => &mut self.ui_volume,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowModeSetting {
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+impl WindowModeSetting {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WindowModeSetting::Windowed => "Windowed",
+            WindowModeSetting::Borderless => "Borderless",
+            WindowModeSetting::Fullscreen => "Fullscreen",
+        }
+    }
+
+    fn next(&self) -> Self {
+        match self {
+            WindowModeSetting::Windowed => WindowModeSetting::Borderless,
+            WindowModeSetting::Borderless => WindowModeSetting::Fullscreen,
+            WindowModeSetting::Fullscreen => WindowModeSetting::Windowed,
+        }
+    }
+}
+
+impl From<WindowModeSetting> for WindowMode {
+    fn from(value: WindowModeSetting) -> Self {
+        match value {
+            WindowModeSetting::Windowed => WindowMode::Windowed,
+            WindowModeSetting::Borderless => WindowMode::BorderlessFullscreen,
+            WindowModeSetting::Fullscreen => WindowMode::Fullscreen,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VolumeKind {
+    Master,
+    Sfx,
+    Music,
+    Ambience,
+    Ui,
+}
+
+impl VolumeKind {
+    pub const ALL: [VolumeKind; 5] =
+        [VolumeKind::Master, VolumeKind::Sfx, VolumeKind::Music, VolumeKind::Ambience, VolumeKind::Ui];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VolumeKind::Master => "Master volume",
+            VolumeKind::Sfx => "Sound effects",
+            VolumeKind::Music => "Music",
+            VolumeKind::Ambience => "Ambience",
+            VolumeKind::Ui => "Menu sounds",
+        }
+    }
+}
+
+/// Things the player can do with the keyboard
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyAction {
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+    ToggleReducedMotion,
+    Pause,
+}
+
+impl KeyAction {
+    pub const ALL: [KeyAction; 9] = [
+        KeyAction::PanUp,
+        KeyAction::PanDown,
+        KeyAction::PanLeft,
+        KeyAction::PanRight,
+        KeyAction::ZoomIn,
+        KeyAction::ZoomOut,
+        KeyAction::ZoomReset,
+        KeyAction::ToggleReducedMotion,
+        KeyAction::Pause,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyAction::PanUp => "Pan up",
+            KeyAction::PanDown => "Pan down",
+            KeyAction::PanLeft => "Pan left",
+            KeyAction::PanRight => "Pan right",
+            KeyAction::ZoomIn => "Zoom in",
+            KeyAction::ZoomOut => "Zoom out",
+            KeyAction::ZoomReset => "Fit level",
+            KeyAction::ToggleReducedMotion => "Reduced motion",
+            KeyAction::Pause => "Pause",
+        }
+    }
+
+    fn default_key(&self) -> KeyCode {
+        match self {
+            KeyAction::PanUp => KeyCode::W,
+            KeyAction::PanDown => KeyCode::S,
+            KeyAction::PanLeft => KeyCode::A,
+            KeyAction::PanRight => KeyCode::D,
+            KeyAction::ZoomIn => KeyCode::Equals,
+            KeyAction::ZoomOut => KeyCode::Minus,
+            KeyAction::ZoomReset => KeyCode::Home,
+            KeyAction::ToggleReducedMotion => KeyCode::F9,
+            KeyAction::Pause => KeyCode::Escape,
+        }
+    }
+}
+
+/// Keys bound to each action, actions missing from the config use their default key
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct Keybinds {
+    binds: Vec<Keybind>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct Keybind {
+    action: KeyAction,
+    #[serde(serialize_with = "serialize_key", deserialize_with = "deserialize_key")]
+    key: KeyCode,
+}
+
+impl Keybinds {
+    pub fn key(&self, action: KeyAction) -> KeyCode {
+        self.binds.iter().find(|bind| bind.action == action).map_or(action.default_key(), |bind| bind.key)
+    }
+
+    fn bind(&mut self, action: KeyAction, key: KeyCode) {
+        match self.binds.iter_mut().find(|bind| bind.action == action) {
+            Some(bind) => bind.key = key,
+            None => self.binds.push(Keybind { action, key }),
+        }
+    }
+
+    pub fn pressed(&self, action: KeyAction, keeb: &Input<KeyCode>) -> bool {
+        keeb.pressed(self.key(action))
+    }
+
+    pub fn just_pressed(&self, action: KeyAction, keeb: &Input<KeyCode>) -> bool {
+        keeb.just_pressed(self.key(action))
+    }
+}
+
+/// Keys that can be bound to an action, also how they are written in the config file
+const BINDABLE_KEYS: [(KeyCode, &str); 59] = [
+    (KeyCode::A, "A"),
+    (KeyCode::B, "B"),
+    (KeyCode::C, "C"),
+    (KeyCode::D, "D"),
+    (KeyCode::E, "E"),
+    (KeyCode::F, "F"),
+    (KeyCode::G, "G"),
+    (KeyCode::H, "H"),
+    (KeyCode::I, "I"),
+    (KeyCode::J, "J"),
+    (KeyCode::K, "K"),
+    (KeyCode::L, "L"),
+    (KeyCode::M, "M"),
+    (KeyCode::N, "N"),
+    (KeyCode::O, "O"),
+    (KeyCode::P, "P"),
+    (KeyCode::Q, "Q"),
+    (KeyCode::R, "R"),
+    (KeyCode::S, "S"),
+    (KeyCode::T, "T"),
+    (KeyCode::U, "U"),
+    (KeyCode::V, "V"),
+    (KeyCode::W, "W"),
+    (KeyCode::X, "X"),
+    (KeyCode::Y, "Y"),
+    (KeyCode::Z, "Z"),
+    (KeyCode::Key0, "0"),
+    (KeyCode::Key1, "1"),
+    (KeyCode::Key2, "2"),
+    (KeyCode::Key3, "3"),
+    (KeyCode::Key4, "4"),
+    (KeyCode::Key5, "5"),
+    (KeyCode::Key6, "6"),
+    (KeyCode::Key7, "7"),
+    (KeyCode::Key8, "8"),
+    (KeyCode::Key9, "9"),
+    (KeyCode::F1, "F1"),
+    (KeyCode::F2, "F2"),
+    (KeyCode::F3, "F3"),
+    (KeyCode::F4, "F4"),
+    (KeyCode::F5, "F5"),
+    (KeyCode::F6, "F6"),
+    (KeyCode::F7, "F7"),
+    (KeyCode::F8, "F8"),
+    (KeyCode::F9, "F9"),
+    (KeyCode::F10, "F10"),
+    (KeyCode::Up, "Up"),
+    (KeyCode::Down, "Down"),
+    (KeyCode::Left, "Left"),
+    (KeyCode::Right, "Right"),
+    (KeyCode::Space, "Space"),
+    (KeyCode::Home, "Home"),
+    (KeyCode::End, "End"),
+    (KeyCode::PageUp, "PageUp"),
+    (KeyCode::PageDown, "PageDown"),
+    (KeyCode::Equals, "="),
+    (KeyCode::Minus, "-"),
+    (KeyCode::Tab, "Tab"),
+    (KeyCode::Escape, "Escape"),
+];
+
+pub fn key_name(key: KeyCode) -> &'static str {
+    BINDABLE_KEYS.iter().find(|(k, _)| *k == key).map_or("?", |(_, name)| name)
+}
+
+fn serialize_key<S: Serializer>(key: &KeyCode, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(key_name(*key))
+}
+
+fn deserialize_key<'de, D: Deserializer<'de>>(deserializer: D) -> Result<KeyCode, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    BINDABLE_KEYS
+        .iter()
+        .find(|(_, n)| n.eq_ignore_ascii_case(&name))
+        .map(|(key, _)| *key)
+        .ok_or_else(|| serde::de::Error::custom(format!("{name} is not a key that can be bound")))
+}
+
+/// Changes the player can make from the settings screen
+#[derive(Event, Clone, Copy, Debug)]
+pub enum SettingsChange {
+    Volume {
+        kind: VolumeKind,
+        up: bool,
+    },
+    CycleWindowMode,
+    CycleResolution,
+    ToggleVsync,
+    ToggleReducedMotion,
+    /// Waits for the next key press and binds it to the action
+    Rebind(KeyAction),
+}
+
+/// The action waiting on a key press to bind to, if any
+#[derive(Resource, Default)]
+pub struct Rebinding(pub Option<KeyAction>);
+
+fn handle_settings_changes(
+    mut ev_change: EventReader<SettingsChange>,
+    mut settings: ResMut<Settings>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    for ev in ev_change.read() {
+        match *ev {
+            SettingsChange::Volume { kind, up } => {
+                let step = if up { VOLUME_STEP } else { -VOLUME_STEP };
+                let volume = settings.volume_mut(kind);
+                // rounded so repeated steps don't drift away from the tenths
+                *volume = ((*volume + step) * 10.0).round().clamp(0.0, 10.0) / 10.0;
+            }
+            SettingsChange::CycleWindowMode => settings.window_mode = settings.window_mode.next(),
+            SettingsChange::CycleResolution => {
+                let current = RESOLUTIONS.iter().position(|res| *res == settings.resolution);
+                settings.resolution = RESOLUTIONS[current.map_or(0, |idx| (idx + 1) % RESOLUTIONS.len())];
+            }
+            SettingsChange::ToggleVsync => settings.vsync = !settings.vsync,
+            SettingsChange::ToggleReducedMotion => settings.reduced_motion = !settings.reduced_motion,
+            SettingsChange::Rebind(action) => {
+                // pressing the same binding again cancels it
+                rebinding.0 = if rebinding.0 == Some(action) { None } else { Some(action) };
+            }
+        }
+    }
+}
+
+fn capture_rebind(mut rebinding: ResMut<Rebinding>, mut settings: ResMut<Settings>, keeb: Res<Input<KeyCode>>) {
+    let Some(action) = rebinding.0 else {
+        return;
+    };
+    let Some(key) = keeb.get_just_pressed().find(|key| BINDABLE_KEYS.iter().any(|(k, _)| k == *key)) else {
+        return;
+    };
+    info!("bound {:?} to {}", action, key_name(*key));
+    settings.keybinds.bind(action, *key);
+    rebinding.0 = None;
+}
+
+/// The pause key backs out of the settings, it is left alone while a key is being rebound so it can be picked
+fn close_settings_on_key(
+    keeb: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    mut next_menu: ResMut<NextState<SettingsMenu>>,
+) {
+    if settings.keybinds.just_pressed(KeyAction::Pause, &keeb) {
+        next_menu.set(SettingsMenu::Closed);
+    }
+}
+
+/// The music volume is applied by the music layers themselves, setting it on the channel would undo their fades
+fn apply_audio_settings(
+    settings: Res<Settings>,
+    sfx: Res<AudioChannel<SfxChannel>>,
+    ambience: Res<AudioChannel<AmbienceChannel>>,
+    ui: Res<AudioChannel<UiChannel>>,
+) {
+    let volume = |kind| (settings.master_volume * settings.volume(kind)) as f64;
+    sfx.set_volume(volume(VolumeKind::Sfx));
+    ambience.set_volume(volume(VolumeKind::Ambience));
+    ui.set_volume(volume(VolumeKind::Ui));
+}
+
+/// Only touches the window size when the setting itself changed so resizing the window by hand sticks
+fn apply_window_settings(
+    settings: Res<Settings>,
+    mut q_window: Query<&mut Window, With<PrimaryWindow>>,
+    mut applied_resolution: Local<Option<(u32, u32)>>,
+) {
+    let Ok(mut window) = q_window.get_single_mut() else {
+        return;
+    };
+    let mode = settings.window_mode.into();
+    if window.mode != mode {
+        window.mode = mode;
+    }
+    let present_mode = settings.present_mode();
+    if window.present_mode != present_mode {
+        window.present_mode = present_mode;
+    }
+    if *applied_resolution != Some(settings.resolution) {
+        window.resolution.set(settings.resolution.0 as f32, settings.resolution.1 as f32);
+        *applied_resolution = Some(settings.resolution);
+    }
+}
+
+/// Skips the frame the settings were loaded in since nothing new needs saving then
+fn settings_changed(settings: Res<Settings>) -> bool {
+    settings.is_changed() && !settings.is_added()
+}
+
+fn save_settings(settings: Res<Settings>) {
+    match save_json5(SETTINGS_PATH, &*settings) {
+        Ok(()) => debug!("saved settings"),
+        Err(e) => error!("could not save settings to {}: {}", SETTINGS_PATH, e),
+    }
+}
@@ -1,7 +1,10 @@
-use bevy::prelude::{in_state, Event, EventReader, IntoSystemConfigs, Plugin, ResMut, Resource, Update};
+use bevy::{
+    log::info,
+    prelude::{in_state, Event, EventReader, EventWriter, IntoSystemConfigs, Plugin, ResMut, Resource, Update},
+};
 use serde::Deserialize;
 
-use crate::AppState;
+use crate::{expedition::PauseState, AppState};
 
 pub struct StabilityPlugin;
 
@@ -9,32 +12,111 @@ impl Plugin for StabilityPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.init_resource::<Stability>()
             .add_event::<StabilityDamage>()
-            .add_systems(Update, (handle_stability_damage).run_if(in_state(AppState::Expedition)));
+            .add_event::<StabilityWarning>()
+            .add_event::<CaveIn>()
+            .add_systems(
+                Update,
+                (handle_stability_damage).run_if(in_state(AppState::Expedition)).run_if(in_state(PauseState::Running)),
+            );
     }
 }
 
 /// Describes the "stamina" meter of the expedition
 /// Reach 0 and enter chaos cave mode
-#[derive(Resource)]
+#[derive(Resource, Default)]
 pub struct Stability {
     pub remaining: i32,
+    /// What the expedition started with
+    pub start: i32,
+    pub profile: LevelStability,
+    pub thresholds: StabilityThresholds,
 }
 
 impl Stability {
-    pub fn new(value: i32) -> Self {
-        Self { remaining: value }
+    pub fn new(profile: LevelStability) -> Self {
+        let start = profile.start();
+        Self { remaining: start, start, profile, thresholds: profile.thresholds() }
     }
-}
 
-impl Default for Stability {
-    fn default() -> Self {
-        Self { remaining: 0 }
+    /// Overrides where the cave becomes shaky and critical, for levels that set their own thresholds
+    pub fn with_thresholds(self, thresholds: StabilityThresholds) -> Self {
+        Self { thresholds, ..self }
+    }
+
+    /// Overrides how much stability the expedition starts with, used for testing levels
+    pub fn with_start(self, start: i32) -> Self {
+        Self { remaining: start, start, ..self }
+    }
+
+    /// How much stability is left out of what the expedition started with, from 0 to 1
+    pub fn fraction(&self) -> f32 {
+        if self.start <= 0 {
+            return 0.0;
+        }
+        (self.remaining as f32 / self.start as f32).clamp(0.0, 1.0)
+    }
+
+    pub fn level(&self) -> StabilityLevel {
+        self.thresholds.level_at(self.fraction())
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub enum LevelStability {
+    #[default]
     Normal,
+    /// Starts with less and starts to give way sooner
+    Fragile,
+}
+
+/// How close the cave is to coming down, each one is worse than the last
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StabilityLevel {
+    Stable,
+    Shaky,
+    Critical,
+}
+
+impl LevelStability {
+    pub fn start(&self) -> i32 {
+        match self {
+            LevelStability::Normal => 10000,
+            LevelStability::Fragile => 6000,
+        }
+    }
+
+    /// Thresholds used by levels of this profile that don't set their own
+    pub fn thresholds(&self) -> StabilityThresholds {
+        match self {
+            LevelStability::Normal => StabilityThresholds { shaky: 0.5, critical: 0.25 },
+            LevelStability::Fragile => StabilityThresholds { shaky: 0.6, critical: 0.35 },
+        }
+    }
+}
+
+/// Fraction of the starting stability left where the cave becomes shaky and then critical
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct StabilityThresholds {
+    pub shaky: f32,
+    pub critical: f32,
+}
+
+impl Default for StabilityThresholds {
+    fn default() -> Self {
+        LevelStability::default().thresholds()
+    }
+}
+
+impl StabilityThresholds {
+    pub fn level_at(&self, fraction: f32) -> StabilityLevel {
+        if fraction < self.critical {
+            StabilityLevel::Critical
+        } else if fraction < self.shaky {
+            StabilityLevel::Shaky
+        } else {
+            StabilityLevel::Stable
+        }
+    }
 }
 
 #[derive(Event)]
@@ -46,10 +128,38 @@ impl StabilityDamage {
     pub fn new(value: u32) -> Self {
         Self { amt: value }
     }
+
+    pub fn amount(&self) -> u32 {
+        self.amt
+    }
 }
 
-fn handle_stability_damage(mut stability: ResMut<Stability>, mut ev_damage: EventReader<StabilityDamage>) {
+/// Sent when the stability drops into a worse level
+#[derive(Event)]
+pub struct StabilityWarning(pub StabilityLevel);
+
+/// Sent once when the last of the stability is used up and the cave comes down
+#[derive(Event)]
+pub struct CaveIn;
+
+fn handle_stability_damage(
+    mut stability: ResMut<Stability>,
+    mut ev_damage: EventReader<StabilityDamage>,
+    mut ev_warning: EventWriter<StabilityWarning>,
+    mut ev_cave_in: EventWriter<CaveIn>,
+) {
     for ev in ev_damage.read() {
+        let before = stability.level();
+        let was_standing = stability.remaining > 0;
         stability.remaining -= ev.amt as i32;
+        if was_standing && stability.remaining <= 0 {
+            info!("the cave has come down");
+            ev_cave_in.send(CaveIn);
+        }
+        let after = stability.level();
+        if after > before {
+            info!("stability is now {:?}", after);
+            ev_warning.send(StabilityWarning(after));
+        }
     }
 }
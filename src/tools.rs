@@ -1,36 +1,54 @@
+use std::iter::{once, successors};
+
 use bevy::{
-    log::info,
+    log::{info, warn},
     prelude::{
-        on_event, App, Entity, Event, EventReader, IntoSystemConfigs, Plugin, Query, Res, ResMut, Resource, Update,
+        on_event, resource_changed, App, Entity, Event, EventReader, IntoSystemConfigs, OnEnter, Plugin, Query, Res,
+        ResMut, Resource, Update,
     },
 };
 use bevy_mod_picking::{
     events::Down,
     prelude::{ListenerInput, Pointer},
 };
+use serde::{Deserialize, Serialize};
 
-use crate::{expedition::in_area_state, ui::prelude::UITool, SystemOrder};
+use crate::{
+    cli::LaunchArgs,
+    data_read::{LevelDb, ShopDb},
+    expedition::{in_area_state, ActiveLevel},
+    save::Progress,
+    ui::prelude::UITool,
+    AppState, SystemOrder,
+};
 
 pub struct ToolPlugin;
 
 impl Plugin for ToolPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<ToolUnlocks>()
-            .init_resource::<ActiveTool>()
+        app.init_resource::<ActiveTool>()
+            .init_resource::<Loadout>()
             .add_event::<ToolUnlockEvent>()
             .add_event::<SwitchTool>()
+            .add_systems(OnEnter(AppState::Expedition), pick_loadout)
             .add_systems(Update, (switch_tool_from_ui,).run_if(on_event::<SwitchTool>()).in_set(SystemOrder::Logic))
+            .add_systems(
+                Update,
+                (keep_active_tool_in_loadout).run_if(resource_changed::<Loadout>()).in_set(SystemOrder::Logic),
+            )
             .add_systems(Update, (unlock_tool).run_if(in_area_state));
     }
 }
 
 #[derive(Event)]
-pub struct ToolUnlockEvent(ToolType);
+pub struct ToolUnlockEvent(pub ToolType);
 
 #[derive(Event)]
 pub struct SwitchTool(Entity);
 
-#[derive(Resource)]
+/// Which tools the player can use, kept in the save
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
 pub struct ToolUnlocks {
     tiny_hammer: Lock,
     pickaxe_horizontal: Lock,
@@ -41,7 +59,127 @@ pub struct ToolUnlocks {
 #[derive(Resource, Default)]
 pub struct ActiveTool(pub ToolType);
 
-#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+/// Which tools a level lets the player bring
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct ToolRules {
+    /// Only these tools can be brought, when not set any unlocked tool can be
+    pub allowed: Option<Vec<ToolType>>,
+    /// Always brought, they are lent out for the level if they haven't been unlocked yet
+    pub required: Vec<ToolType>,
+    /// Most tools that can be brought at once, no limit when not set
+    pub loadout_size: Option<usize>,
+}
+
+impl ToolRules {
+    /// Every tool the player could bring into the level.
+    /// When none of the allowed tools have been unlocked they are all lent out so the level can still be played
+    pub fn available(&self, unlocks: &ToolUnlocks) -> Vec<ToolType> {
+        let allowed = |tool: &ToolType| self.allowed.as_ref().map_or(true, |allowed| allowed.contains(tool));
+        let available = ToolType::ALL
+            .into_iter()
+            .filter(|tool| self.required.contains(tool) || (unlocks.is_unlocked(tool) && allowed(tool)))
+            .collect::<Vec<_>>();
+        if available.is_empty() {
+            ToolType::ALL.into_iter().filter(allowed).collect()
+        } else {
+            available
+        }
+    }
+
+    /// Required tools always fit even when there are more of them than the loadout size, and at least one tool
+    /// always fits
+    pub fn loadout_size(&self) -> Option<usize> {
+        self.loadout_size.map(|size| size.max(self.required.len()).max(1))
+    }
+
+    /// The same rules with `tool` required as well, lending it out if it hasn't been unlocked yet
+    pub fn with_required(mut self, tool: ToolType) -> Self {
+        if !self.required.contains(&tool) {
+            self.required.push(tool);
+        }
+        self
+    }
+
+    /// Fills the loadout with the required tools, then the `preferred` ones in order until it is full.
+    /// Falls back to the first available tool so there is always something to mine with
+    pub fn pick_loadout(&self, unlocks: &ToolUnlocks, preferred: impl IntoIterator<Item = ToolType>) -> Loadout {
+        let available = self.available(unlocks);
+        let size = self.loadout_size();
+        let mut picked = self.required.clone();
+        for tool in preferred.into_iter().chain(available.iter().copied()) {
+            if size.is_some_and(|size| picked.len() >= size) {
+                break;
+            }
+            if available.contains(&tool) && !picked.contains(&tool) {
+                picked.push(tool);
+            }
+        }
+        if picked.is_empty() {
+            picked.push(available.first().copied().unwrap_or_default());
+        }
+        Loadout { picked, available, required: self.required.clone(), size }
+    }
+}
+
+/// The tools brought into the current expedition, picked before mining starts
+#[derive(Resource, Default)]
+pub struct Loadout {
+    picked: Vec<ToolType>,
+    available: Vec<ToolType>,
+    required: Vec<ToolType>,
+    size: Option<usize>,
+}
+
+impl Loadout {
+    pub fn contains(&self, tool: &ToolType) -> bool {
+        self.picked.contains(tool)
+    }
+
+    pub fn is_required(&self, tool: &ToolType) -> bool {
+        self.required.contains(tool)
+    }
+
+    pub fn picked(&self) -> &[ToolType] {
+        &self.picked
+    }
+
+    pub fn available(&self) -> &[ToolType] {
+        &self.available
+    }
+
+    /// Most tools that can be brought, `None` when there is no limit
+    pub fn size(&self) -> Option<usize> {
+        self.size
+    }
+
+    /// Takes the tool out of the loadout or puts it in if there is room, required tools and the last tool are kept
+    pub fn toggle(&mut self, tool: ToolType) {
+        if self.is_required(&tool) || !self.available.contains(&tool) {
+            return;
+        }
+        match self.picked.iter().position(|picked| *picked == tool) {
+            Some(idx) if self.picked.len() > 1 => {
+                self.picked.remove(idx);
+            }
+            None if self.size.map_or(true, |size| self.picked.len() < size) => self.picked.push(tool),
+            _ => {}
+        }
+    }
+
+    pub fn tools_for_ui(&self) -> Vec<UITool> {
+        let mut tools = vec![];
+        if self.contains(&ToolType::TinyHammer) {
+            tools.push(UITool::TinyHammer);
+        }
+        if self.picked.iter().any(|tool| matches!(tool, ToolType::Pickaxe { .. })) {
+            tools.push(UITool::Pickaxe);
+        }
+        tools
+    }
+}
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ToolType {
     #[default]
     TinyHammer,
@@ -50,61 +188,141 @@ pub enum ToolType {
     },
 }
 
-impl ToolType {
-    /// Checks if the tool type is Pickaxe with no concern for the rotation
-    fn is_pickaxe(&self) -> bool {
-        matches!(self, ToolType::Pickaxe { .. })
-    }
-}
-
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PickaxeRotation {
     Horizontal,
     Vertical,
     Cross,
 }
 
-#[derive(Default)]
-enum Lock {
-    Unlocked,
-    #[default]
-    Locked,
+impl ToolType {
+    pub const ALL: [ToolType; 4] = [
+        ToolType::TinyHammer,
+        ToolType::Pickaxe { rotation: PickaxeRotation::Horizontal },
+        ToolType::Pickaxe { rotation: PickaxeRotation::Vertical },
+        ToolType::Pickaxe { rotation: PickaxeRotation::Cross },
+    ];
+
+    /// Name shown to the player
+    pub fn name(&self) -> String {
+        match self {
+            ToolType::TinyHammer => "Tiny Hammer".to_string(),
+            ToolType::Pickaxe { rotation } => format!("{:?} Pickaxe", rotation),
+        }
+    }
 }
 
-impl ToolUnlocks {
-    pub fn get_tools_for_ui(&self) -> Vec<UITool> {
-        let mut unlocked = vec![];
-        if self.tiny_hammer.is_unlocked() {
-            unlocked.push(UITool::TinyHammer);
+impl PickaxeRotation {
+    /// The rotation the pickaxe switches to when it is clicked again
+    fn next(self) -> PickaxeRotation {
+        match self {
+            PickaxeRotation::Horizontal => PickaxeRotation::Vertical,
+            PickaxeRotation::Vertical => PickaxeRotation::Cross,
+            PickaxeRotation::Cross => PickaxeRotation::Horizontal,
         }
-        if self.pickaxe_vertical.is_unlocked()
-            || self.pickaxe_horizontal.is_unlocked()
-            || self.pickaxe_cross.is_unlocked()
-        {
-            unlocked.push(UITool::Pickaxe);
+    }
+}
+
+/// Levels bought of each tool upgrade, kept in the save
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct ToolUpgrades {
+    damage: u32,
+    reach: u32,
+    stability: u32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+pub enum UpgradeKind {
+    /// Every tile hit takes extra damage
+    Damage,
+    /// Pickaxe swings reach further out from the clicked tile
+    Reach,
+    /// Each swing costs less stability
+    Stability,
+}
+
+impl UpgradeKind {
+    pub const ALL: [UpgradeKind; 3] = [UpgradeKind::Damage, UpgradeKind::Reach, UpgradeKind::Stability];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            UpgradeKind::Damage => "Sharpened Tips",
+            UpgradeKind::Reach => "Longer Handles",
+            UpgradeKind::Stability => "Padded Grips",
         }
+    }
+}
 
-        unlocked
+impl ToolUpgrades {
+    pub fn level(&self, kind: UpgradeKind) -> u32 {
+        match kind {
+            UpgradeKind::Damage => self.damage,
+            UpgradeKind::Reach => self.reach,
+            UpgradeKind::Stability => self.stability,
+        }
     }
 
-    #[allow(unused)]
-    pub fn get_total_unlocks(&self) -> u8 {
-        let mut total = 0;
+    /// Cost of the next level of the upgrade, `None` when it is maxed out
+    pub fn next_cost(&self, kind: UpgradeKind, shop: &ShopDb) -> Option<u32> {
+        shop.upgrade_costs(kind).get(self.level(kind) as usize).copied()
+    }
 
-        if self.tiny_hammer.is_unlocked() {
-            total += 1;
+    pub fn add_level(&mut self, kind: UpgradeKind) {
+        match kind {
+            UpgradeKind::Damage => self.damage += 1,
+            UpgradeKind::Reach => self.reach += 1,
+            UpgradeKind::Stability => self.stability += 1,
         }
-        if self.pickaxe_vertical.is_unlocked() {
-            total += 1;
-        }
-        if self.pickaxe_horizontal.is_unlocked() {
-            total += 1;
+    }
+
+    pub fn extra_damage(&self) -> usize {
+        self.damage as usize
+    }
+
+    /// How many tiles out from the clicked tile a pickaxe hits
+    pub fn reach(&self) -> i32 {
+        1 + self.reach as i32
+    }
+
+    /// Takes 10% off of the stability cost for each level
+    pub fn stability_cost(&self, base: u32) -> u32 {
+        base * 10u32.saturating_sub(self.stability) / 10
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+enum Lock {
+    Unlocked,
+    #[default]
+    Locked,
+}
+
+impl ToolUnlocks {
+    pub fn is_unlocked(&self, tool: &ToolType) -> bool {
+        match tool {
+            ToolType::TinyHammer => self.tiny_hammer.is_unlocked(),
+            ToolType::Pickaxe { rotation } => self.pickaxe(*rotation).is_unlocked(),
         }
-        if self.pickaxe_cross.is_unlocked() {
-            total += 1;
+    }
+
+    pub fn unlock(&mut self, tool: &ToolType) {
+        match tool {
+            ToolType::TinyHammer => self.tiny_hammer = Lock::Unlocked,
+            ToolType::Pickaxe { rotation } => match rotation {
+                PickaxeRotation::Horizontal => self.pickaxe_horizontal = Lock::Unlocked,
+                PickaxeRotation::Vertical => self.pickaxe_vertical = Lock::Unlocked,
+                PickaxeRotation::Cross => self.pickaxe_cross = Lock::Unlocked,
+            },
         }
+    }
 
-        total
+    fn pickaxe(&self, rotation: PickaxeRotation) -> &Lock {
+        match rotation {
+            PickaxeRotation::Horizontal => &self.pickaxe_horizontal,
+            PickaxeRotation::Vertical => &self.pickaxe_vertical,
+            PickaxeRotation::Cross => &self.pickaxe_cross,
+        }
     }
 }
 
@@ -131,12 +349,40 @@ impl From<ListenerInput<Pointer<Down>>> for SwitchTool {
     }
 }
 
-// TODO: check unlocked_tools before switching and fall back to switching to something else
-// log a warning for funsies
+/// Sets up the loadout for the level that was just entered, starting from what was brought last time
+fn pick_loadout(
+    mut loadout: ResMut<Loadout>,
+    active_tool: Res<ActiveTool>,
+    level_db: Res<LevelDb>,
+    active_level: Res<ActiveLevel>,
+    progress: Res<Progress>,
+    launch_args: Option<Res<LaunchArgs>>,
+) {
+    let mut rules = level_db
+        .get(&active_level.area.to_string())
+        .and_then(|area| area.levels.get(active_level.level_idx))
+        .map_or_else(ToolRules::default, |level| level.tools.clone());
+    if let Some(tool) = launch_args.and_then(|args| args.launched_tool(&active_level)) {
+        rules = rules.with_required(tool);
+    }
+    let preferred = once(active_tool.0).chain(loadout.picked.iter().copied()).collect::<Vec<_>>();
+    *loadout = rules.pick_loadout(&progress.tools, preferred);
+    info!("bringing {:?} out of {:?}", loadout.picked, loadout.available);
+}
+
+fn keep_active_tool_in_loadout(loadout: Res<Loadout>, mut active_tool: ResMut<ActiveTool>) {
+    if loadout.contains(&active_tool.0) {
+        return;
+    }
+    if let Some(tool) = loadout.picked.first() {
+        active_tool.0 = *tool;
+    }
+}
+
 fn switch_tool_from_ui(
     mut ev_switch: EventReader<SwitchTool>,
     mut active_tool: ResMut<ActiveTool>,
-    _unlocked_tools: Res<ToolUnlocks>,
+    loadout: Res<Loadout>,
     q_ui_tools: Query<&UITool>,
 ) {
     for ev in ev_switch.read() {
@@ -148,25 +394,23 @@ fn switch_tool_from_ui(
         let actual_switch = match new_switch_tool {
             UITool::TinyHammer => ToolType::TinyHammer,
             UITool::Pickaxe => {
-                if active_tool.0.is_pickaxe() {
-                    match active_tool.0 {
-                        ToolType::Pickaxe { rotation } => {
-                            let rotation = match rotation {
-                                PickaxeRotation::Horizontal => PickaxeRotation::Vertical,
-                                PickaxeRotation::Vertical => PickaxeRotation::Cross,
-                                PickaxeRotation::Cross => PickaxeRotation::Horizontal,
-                            };
-                            ToolType::Pickaxe { rotation }
-                        }
-                        _ => unreachable!("Unreachable because we know active tool is a pickaxe"),
-                    }
-                } else {
-                    ToolType::Pickaxe { rotation: PickaxeRotation::Horizontal }
-                }
+                let first = match active_tool.0 {
+                    ToolType::Pickaxe { rotation } => rotation.next(),
+                    _ => PickaxeRotation::Horizontal,
+                };
+                // skip over the rotations that were not brought
+                let rotation = successors(Some(first), |rotation| Some(rotation.next()))
+                    .take(3)
+                    .find(|rotation| loadout.contains(&ToolType::Pickaxe { rotation: *rotation }))
+                    .unwrap_or(first);
+                ToolType::Pickaxe { rotation }
             }
         };
 
-        // DOWN HERE: check unlocked tools
+        if !loadout.contains(&actual_switch) {
+            warn!("{:?} is not in the loadout, not switching to it", actual_switch);
+            continue;
+        }
 
         active_tool.0 = actual_switch;
         info!("switched to {:?}", actual_switch);
@@ -197,16 +441,9 @@ fn switch_tool_from_ui(
 //     }
 // }
 
-fn unlock_tool(mut ev_tool_unlocks: EventReader<ToolUnlockEvent>, mut curr_tool_unlocks: ResMut<ToolUnlocks>) {
+fn unlock_tool(mut ev_tool_unlocks: EventReader<ToolUnlockEvent>, mut progress: ResMut<Progress>) {
     for ev in ev_tool_unlocks.read() {
-        match ev.0 {
-            ToolType::TinyHammer => curr_tool_unlocks.tiny_hammer = Lock::Unlocked,
-            ToolType::Pickaxe { rotation } => match rotation {
-                PickaxeRotation::Horizontal => curr_tool_unlocks.pickaxe_horizontal = Lock::Unlocked,
-                PickaxeRotation::Vertical => curr_tool_unlocks.pickaxe_vertical = Lock::Unlocked,
-                PickaxeRotation::Cross => curr_tool_unlocks.pickaxe_cross = Lock::Unlocked,
-            },
-        }
+        progress.tools.unlock(&ev.0);
     }
 }
 
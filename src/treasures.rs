@@ -2,17 +2,18 @@ use bevy::{
     log::{error, info},
     prelude::*,
     sprite::{SpriteSheetBundle, TextureAtlasSprite},
-    utils::HashMap,
 };
-use rand::{seq::SliceRandom, Rng};
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     assets::SpriteAssets,
-    data_read::{TreasureInfo, TREASURE_DB},
-    expedition::{ExpeditionPersist, ExpeditionStatus, InitExpedition},
+    data_read::{DropEntry, LevelDb, PlaceableShape, Rarity, TreasureDb, TreasureInfo},
+    expedition::{ActiveLevel, ExpeditionPersist, ExpeditionPhase, InitExpedition, PhaseChange},
     mining::{MiningGrid, MiningTile},
-    point::{idx_to_xy, xy_to_idx, UPoint},
-    AppState, SPRITE_PX_X, SPRITE_PX_Y,
+    point::{tile_to_world, Grid, IPoint, UPoint},
+    save::Progress,
+    AppState,
 };
 
 pub struct TreasurePlugin;
@@ -22,131 +23,245 @@ impl Plugin for TreasurePlugin {
         app.add_systems(OnEnter(AppState::Expedition), init_treasures)
             .add_systems(
                 Update,
-                (check_treasure_uncovered,).run_if(in_state(AppState::Expedition)).run_if(on_event::<CheckTreasure>()),
+                (check_treasure_uncovered,)
+                    .run_if(in_state(ExpeditionPhase::Mining))
+                    .run_if(on_event::<CheckTreasure>()),
             )
-            .add_event::<CheckTreasure>();
+            .add_systems(
+                OnEnter(ExpeditionPhase::Cleared),
+                (update_pity_counter, collect_treasures, reveal_missed_treasures),
+            )
+            .add_systems(OnEnter(ExpeditionPhase::LeftEarly), (collect_treasures, reveal_missed_treasures))
+            .add_systems(OnEnter(ExpeditionPhase::Failed), reveal_missed_treasures)
+            .add_systems(Update, fade_rock.run_if(in_state(AppState::Expedition)))
+            .add_event::<CheckTreasure>()
+            .add_event::<TreasureDiscovered>();
     }
 }
 
 #[derive(Component)]
 pub struct Treasure {
-    pub id: u32,           // id into the list of all the treasures
-    pub parts: Vec<usize>, // a list of idx for where the item is
+    pub id: u32,            // id into the list of all the treasures
+    pub parts: Vec<UPoint>, // a list of positions for where the item is
     pub is_discovered: bool,
 }
 
 #[derive(Component)]
 pub struct TreasureTile;
 
-// TODO: use these unused structs or get rid of them
-#[derive(Resource)]
-#[allow(unused)]
+/// Rock over a missed treasure fading away at the end of an expedition
+#[derive(Component)]
+struct FadingRock(Timer);
+
+/// Every treasure the player has brought back from an expedition, kept in the save
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
 pub struct TreasureTrove {
-    treasures: HashMap<u32, TreasureData>,
+    treasures: Vec<TreasureData>,
 }
 
-#[allow(unused)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct TreasureData {
-    times_collected: u32,
+    pub id: u32,
+    pub times_collected: u32,
+    /// How many are still held, selling one at the shop takes it away but it stays in the collection
+    #[serde(default)]
+    pub owned: u32,
+    /// Name of the level the treasure was first brought back from
+    pub first_found: String,
+}
+
+impl TreasureTrove {
+    pub fn get(&self, id: u32) -> Option<&TreasureData> {
+        self.treasures.iter().find(|t| t.id == id)
+    }
+
+    /// Every treasure that has at least one held
+    pub fn owned(&self) -> impl Iterator<Item = &TreasureData> {
+        self.treasures.iter().filter(|t| t.owned > 0)
+    }
+
+    pub fn collect(&mut self, id: u32, level_name: &str) {
+        match self.treasures.iter_mut().find(|t| t.id == id) {
+            Some(data) => {
+                data.times_collected += 1;
+                data.owned += 1;
+            }
+            None => self.treasures.push(TreasureData {
+                id,
+                times_collected: 1,
+                owned: 1,
+                first_found: level_name.to_string(),
+            }),
+        }
+    }
+
+    /// Removes one of the held treasures, false if there were none to take
+    pub fn take_one(&mut self, id: u32) -> bool {
+        match self.treasures.iter_mut().find(|t| t.id == id && t.owned > 0) {
+            Some(data) => {
+                data.owned -= 1;
+                true
+            }
+            None => false,
+        }
+    }
 }
 
 #[derive(Event)]
 pub struct CheckTreasure {}
 
+/// Sent when the last part of a treasure is dug up
+#[derive(Event)]
+pub struct TreasureDiscovered {
+    pub id: u32,
+}
+
 pub struct TreasureGrid {
-    pub treasures: Vec<Option<Entity>>,
-    pub width: usize,
-    pub height: usize,
+    pub treasures: Grid<Option<Entity>>,
 }
 
 impl TreasureGrid {
-    fn new(width: usize, height: usize) -> Self {
-        Self { treasures: vec![None; width * height], width, height }
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { treasures: Grid::new(width, height, None) }
     }
 }
 
 const TREASURE_Z: f32 = 20.0;
+/// Stops placement from looping forever when a treasure cannot fit in the level
+const MAX_PLACEMENT_ATTEMPTS: u32 = 1000;
+/// How see through the rock over a missed treasure is made once the expedition is over
+const MISSED_TREASURE_ROCK_ALPHA: f32 = 0.35;
+const MISSED_TREASURE_FADE_SECS: f32 = 1.5;
 
-fn init_treasures(mut commands: Commands, mut ev_init: EventReader<InitExpedition>, sprites: Res<SpriteAssets>) {
+fn init_treasures(
+    mut commands: Commands,
+    mut ev_init: EventReader<InitExpedition>,
+    sprites: Res<SpriteAssets>,
+    tdb: Res<TreasureDb>,
+    level_db: Res<LevelDb>,
+    active_level: Res<ActiveLevel>,
+    progress: Res<Progress>,
+) {
     let Some(grid) = ev_init.read().next() else {
         info!("entering expedition state, no event to create treasure grid");
         return;
     };
     info!("init treasures");
+    let mut rng = grid.rng(1);
     let mut grid = TreasureGrid::new(grid.size_x, grid.size_y);
-    let mut rng = rand::thread_rng();
 
-    // add treasures
-    let Some(tdb) = TREASURE_DB.get() else {
-        error!("could not find treasure database");
+    // add treasures, a rarer treasure is forced when the area's pity rule kicks in
+    let area_name = active_level.area.to_string();
+    let guaranteed = level_db.get(&area_name).and_then(|area| area.pity.as_ref()).and_then(|rule| {
+        let dry_streak = progress.pity_counters.get(&area_name).copied().unwrap_or(0);
+        rule.is_due(dry_streak).then_some(rule.rarity)
+    });
+    let drop_table = level_db.drop_table(&area_name, active_level.level_idx);
+    let mut pool = build_drop_pool(&tdb, drop_table, guaranteed);
+    if pool.is_empty() && guaranteed.is_some() {
+        warn!("drop table has nothing of rarity {:?}, picking from every treasure", guaranteed);
+        pool = build_drop_pool(&tdb, None, guaranteed);
+    }
+    if let Some(rarity) = guaranteed {
+        info!("pity kicked in, guaranteeing a treasure of at least {:?}", rarity);
+    }
+    let Ok(weights) = WeightedIndex::new(pool.iter().map(|(_, weight, _)| *weight)) else {
+        error!("no valid treasures to place in the drop table");
         return;
     };
 
     let mut total_treasures = 0;
+    let mut attempts = 0;
     while total_treasures < 1 {
+        attempts += 1;
+        if attempts > MAX_PLACEMENT_ATTEMPTS {
+            warn!("gave up placing treasures after {} attempts", MAX_PLACEMENT_ATTEMPTS);
+            break;
+        }
+
         // pick position
-        let left_x = rng.gen_range(0..grid.width);
-        let bottom_y = rng.gen_range(0..grid.height);
+        let left_x = rng.gen_range(0..grid.treasures.width());
+        let bottom_y = rng.gen_range(0..grid.treasures.height());
+        let start = UPoint::new(left_x, bottom_y);
 
-        // check it does not overlap any other treasure
-        let Some(treasure_def) = &tdb.choose(&mut rng) else {
-            return;
-        };
+        // check it does not overlap any other treasure, in any of the orientations it can be placed in
+        let (treasure_def, _, orientations) = &pool[weights.sample(&mut rng)];
+        let shape = &orientations[rng.gen_range(0..orientations.len())];
 
-        if !does_treasure_fit(&grid, treasure_def, UPoint { x: left_x, y: bottom_y }) {
+        if !does_treasure_fit(&grid, &shape.grid, start) {
             continue;
         }
         total_treasures += 1;
 
         // create treasure entity and sprite parts
         let mut treasure_parts = vec![];
-        for (idx, tile) in treasure_def.shape.iter().enumerate() {
-            if tile == &-1 {
-                // ignore tiles that are -1 since that means the treasure does not occupy that spot
-                continue;
-            }
-
-            let treasure_pos = idx_to_xy(idx, treasure_def.width);
-            let new_pos = UPoint { x: treasure_pos.x + left_x, y: bottom_y - treasure_pos.y };
-            let new_idx = xy_to_idx(new_pos.x, new_pos.y, grid.width);
-
+        for (pos, atlas_idx) in placed_parts(&shape.grid, start) {
             commands.spawn((
                 SpriteSheetBundle {
                     texture_atlas: sprites.treasures.clone(),
-                    // sprite: TextureAtlasSprite { color: Color::rgba(1.0, 0.0, 0.0, 1.0), index: *tile as usize, ..Default::default() },
-                    sprite: TextureAtlasSprite::new(*tile as usize),
-                    transform: Transform::from_xyz(
-                        (new_pos.x * SPRITE_PX_X as usize) as f32,
-                        (new_pos.y * SPRITE_PX_Y as usize) as f32,
-                        TREASURE_Z,
-                    ),
+                    sprite: TextureAtlasSprite { index: atlas_idx, flip_x: shape.sprite_mirrored, ..default() },
+                    transform: Transform::from_translation(tile_to_world(pos).extend(TREASURE_Z))
+                        .with_rotation(Quat::from_rotation_z(shape.sprite_rotation.radians())),
                     ..Default::default()
                 },
                 TreasureTile,
                 ExpeditionPersist,
             ));
 
-            treasure_parts.push(new_idx);
-            info!("{:?} contains treasure", new_pos);
+            let Some(pos) = grid.treasures.checked(pos) else {
+                continue;
+            };
+            treasure_parts.push(pos);
+            info!("{:?} contains treasure", pos);
         }
 
-        let parent = commands
-            .spawn((Treasure { id: 0, parts: treasure_parts.clone(), is_discovered: false }, ExpeditionPersist));
-        for treasure_idx in treasure_parts.iter() {
-            grid.treasures[*treasure_idx] = Some(parent.id());
+        let parent = commands.spawn((
+            Treasure { id: treasure_def.id, parts: treasure_parts.clone(), is_discovered: false },
+            ExpeditionPersist,
+        ));
+        for part in treasure_parts.into_iter() {
+            grid.treasures[part] = Some(parent.id());
         }
     }
 }
 
+/// Treasures that can be placed along with their weight and every shape they can be placed as.
+/// Uses each treasure's rarity for the weight when there is no drop table
+pub fn build_drop_pool<'a>(
+    tdb: &'a TreasureDb,
+    drop_table: Option<&[DropEntry]>,
+    min_rarity: Option<Rarity>,
+) -> Vec<(&'a TreasureInfo, u32, Vec<PlaceableShape>)> {
+    let weighted = match drop_table {
+        Some(table) => table
+            .iter()
+            .filter_map(|entry| {
+                let treasure = tdb.get_by_id(entry.treasure);
+                if treasure.is_none() {
+                    warn!("drop table has treasure {} which does not exist", entry.treasure);
+                }
+                treasure.map(|t| (t, entry.weight))
+            })
+            .collect::<Vec<_>>(),
+        None => tdb.iter().map(|t| (t, t.rarity.default_weight())).collect(),
+    };
+
+    weighted
+        .into_iter()
+        .filter(|(t, weight)| *weight > 0 && min_rarity.map_or(true, |rarity| t.rarity >= rarity))
+        .map(|(t, weight)| (t, weight, t.placeable_shapes()))
+        .filter(|(_, _, shapes)| !shapes.is_empty())
+        .collect()
+}
+
 fn check_treasure_uncovered(
-    mut expedition_status: ResMut<ExpeditionStatus>,
+    mut ev_phase: EventWriter<PhaseChange>,
     mut q_treasures: Query<&mut Treasure>,
     q_mining_grid: Query<&MiningGrid>,
     q_mining_tiles: Query<&MiningTile>,
+    mut ev_discovered: EventWriter<TreasureDiscovered>,
 ) {
-    if matches!(*expedition_status, ExpeditionStatus::Cleared) {
-        return;
-    }
     let active_grid = q_mining_grid.single();
 
     let mut discovered_amt = vec![];
@@ -166,6 +281,7 @@ fn check_treasure_uncovered(
 
         if is_uncovered {
             info!("Treasure was discovered.");
+            ev_discovered.send(TreasureDiscovered { id: treasure.id });
             discovered_amt.push(true);
         } else {
             discovered_amt.push(false);
@@ -175,40 +291,33 @@ fn check_treasure_uncovered(
 
     if !discovered_amt.contains(&false) {
         info!("All treasures were discovered");
-        *expedition_status = ExpeditionStatus::Cleared;
+        ev_phase.send(PhaseChange(ExpeditionPhase::Cleared));
     }
 }
 
-fn does_treasure_fit(existing: &TreasureGrid, treasure: &TreasureInfo, start: UPoint) -> bool {
-    for (idx, tile) in treasure.shape.iter().enumerate() {
-        if tile == &-1 {
-            // ignore tiles that are -1 since that means the treasure does not occupy that spot
-            continue;
-        }
-
-        let treasure_grid_offset = idx_to_xy(idx, treasure.width);
-        if treasure_grid_offset.y > start.y {
-            debug!("Offset {:?} is too close to a border.", treasure_grid_offset);
-            return false;
-        }
-
-        let new_pos = UPoint { x: treasure_grid_offset.x + start.x, y: start.y - treasure_grid_offset.y };
-        if new_pos.x >= existing.width || new_pos.y >= existing.height {
-            debug!("Offset {:?} is too close to a border.", treasure_grid_offset);
-            return false;
-        }
+/// Grid positions covered by a shape with its bottom left corner at `start`, along with the atlas index for each
+pub fn placed_parts(shape: &Grid<i32>, start: UPoint) -> impl Iterator<Item = (IPoint, usize)> + '_ {
+    let start = IPoint::from(start);
+    shape
+        .iter()
+        // ignore tiles that are -1 since that means the treasure does not occupy that spot
+        .filter(|(_, tile)| **tile != -1)
+        .map(move |(offset, tile)| (start + IPoint::from(offset), *tile as usize))
+}
 
-        let new_idx = xy_to_idx(new_pos.x, new_pos.y, existing.width);
-        match existing.treasures.get(new_idx) {
-            Some(maybe_treasure) if maybe_treasure.is_some() => {
-                debug!("Position {:?} has treasure already in it", new_pos);
+/// Checks the treasure shape can be placed with its bottom left corner at `start` without leaving the grid or overlapping
+pub fn does_treasure_fit(existing: &TreasureGrid, shape: &Grid<i32>, start: UPoint) -> bool {
+    for (pos, _) in placed_parts(shape, start) {
+        match existing.treasures.checked(pos).map(|pos| existing.treasures[pos]) {
+            Some(Some(_)) => {
+                debug!("Position {:?} has treasure already in it", pos);
                 return false;
             }
-            Some(_empty) => {
+            Some(None) => {
                 continue;
             }
             None => {
-                debug!("Position {:?} is out of bounds of the treasure grid.", new_pos);
+                debug!("Position {:?} is out of bounds of the treasure grid.", pos);
                 return false;
             }
         }
@@ -216,3 +325,67 @@ fn does_treasure_fit(existing: &TreasureGrid, treasure: &TreasureInfo, start: UP
 
     true
 }
+
+/// Counts clears without a treasure of the area's pity rarity, resetting once one is found.
+/// Only runs on a clear, see [`PityRule`](crate::data_read::PityRule)
+fn update_pity_counter(
+    q_treasures: Query<&Treasure>,
+    tdb: Res<TreasureDb>,
+    level_db: Res<LevelDb>,
+    active_level: Res<ActiveLevel>,
+    mut progress: ResMut<Progress>,
+) {
+    let area_name = active_level.area.to_string();
+    let Some(rule) = level_db.get(&area_name).and_then(|area| area.pity.as_ref()) else {
+        return;
+    };
+
+    let found = q_treasures
+        .iter()
+        .filter(|treasure| treasure.is_discovered)
+        .filter_map(|treasure| tdb.get_by_id(treasure.id))
+        .map(|treasure| treasure.rarity)
+        .collect::<Vec<_>>();
+    let dry_streak = progress.pity_counters.entry(area_name).or_default();
+    *dry_streak = rule.next_streak(*dry_streak, &found);
+    debug!("pity counter is now {}", dry_streak);
+}
+
+/// Rock left over treasures that weren't found fades to see through so the player can see what they missed
+fn reveal_missed_treasures(mut commands: Commands, q_treasures: Query<&Treasure>, q_mining_grid: Query<&MiningGrid>) {
+    let Ok(grid) = q_mining_grid.get_single() else {
+        return;
+    };
+    for treasure in q_treasures.iter().filter(|treasure| !treasure.is_discovered) {
+        for tile in treasure.parts.iter().filter_map(|part| grid.rock_tiles[*part]) {
+            commands.entity(tile).insert(FadingRock(Timer::from_seconds(MISSED_TREASURE_FADE_SECS, TimerMode::Once)));
+        }
+    }
+}
+
+fn fade_rock(mut q_fading: Query<(&mut TextureAtlasSprite, &mut FadingRock)>, time: Res<Time>) {
+    for (mut sprite, mut fade) in q_fading.iter_mut() {
+        if fade.0.finished() {
+            continue;
+        }
+        fade.0.tick(time.delta());
+        sprite.color.set_a(1.0 - (1.0 - MISSED_TREASURE_ROCK_ALPHA) * fade.0.percent());
+    }
+}
+
+/// Adds every treasure that was dug up to the player's collection
+fn collect_treasures(
+    q_treasures: Query<&Treasure>,
+    level_db: Res<LevelDb>,
+    active_level: Res<ActiveLevel>,
+    mut progress: ResMut<Progress>,
+) {
+    let level_name = level_db
+        .get(&active_level.area.to_string())
+        .and_then(|area| area.levels.get(active_level.level_idx))
+        .map_or_else(|| active_level.area.to_string(), |level| level.name.clone());
+
+    for treasure in q_treasures.iter().filter(|treasure| treasure.is_discovered) {
+        progress.trove.collect(treasure.id, &level_name);
+    }
+}
@@ -0,0 +1,185 @@
+use bevy::prelude::*;
+
+use super::{cleanup, spawn_menu_buttons, MenuButton, StateUIMaster, TEXT_COLOR};
+use crate::{
+    assets::{SpriteAssets, UiAssets},
+    data_read::{Rarity, TreasureDb, TreasureInfo},
+    expedition::Area,
+    save::Progress,
+    AppState, SPRITE_PX_X,
+};
+
+/// Size of a single treasure tile in the gallery
+const GALLERY_TILE_PX: f32 = (SPRITE_PX_X * 3) as f32;
+const FADED_TEXT_COLOR: Color = Color::rgb(0.45, 0.45, 0.45);
+/// Tint for treasures that have not been found, leaves a dark silhouette of the shape
+const UNDISCOVERED_TINT: Color = Color::rgb(0.15, 0.15, 0.15);
+const CARD_BACKGROUND: Color = Color::rgb(0.1, 0.1, 0.12);
+
+pub struct CollectionUIPlugin;
+
+impl Plugin for CollectionUIPlugin {
+    fn build(&self, app: &mut App) {
+        for curr_area in Area::ALL {
+            app.add_systems(OnEnter(AppState::Collection { curr_area: curr_area.clone() }), setup_collection)
+                .add_systems(OnExit(AppState::Collection { curr_area }), cleanup);
+        }
+        app.add_systems(
+            Update,
+            (cleanup, setup_collection).chain().run_if(in_collection_state).run_if(resource_changed::<TreasureDb>()),
+        );
+    }
+}
+
+fn in_collection_state(app_state: Res<State<AppState>>) -> bool {
+    matches!(app_state.get(), AppState::Collection { .. })
+}
+
+fn setup_collection(
+    mut commands: Commands,
+    fonts: Res<UiAssets>,
+    sprites: Res<SpriteAssets>,
+    tdb: Res<TreasureDb>,
+    progress: Res<Progress>,
+) {
+    debug!("setting up ui for the collection");
+    let found = tdb.iter().filter(|t| progress.trove.get(t.id).is_some()).count();
+    let title_style = TextStyle { font_size: 40.0, color: TEXT_COLOR, font: fonts.text.clone() };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    padding: UiRect::all(Val::Px(20.0)),
+                    ..default()
+                },
+                ..default()
+            },
+            StateUIMaster,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(format!("Collection {}/{}", found, tdb.len()), title_style));
+            spawn_menu_buttons(parent, &fonts, &[MenuButton::Back]);
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_wrap: FlexWrap::Wrap,
+                        justify_content: JustifyContent::Center,
+                        margin: UiRect::top(Val::Px(20.0)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for treasure in tdb.iter() {
+                        spawn_treasure_card(parent, &fonts, &sprites, treasure, &progress);
+                    }
+                });
+        });
+}
+
+fn spawn_treasure_card(
+    parent: &mut ChildBuilder,
+    fonts: &UiAssets,
+    sprites: &SpriteAssets,
+    treasure: &TreasureInfo,
+    progress: &Progress,
+) {
+    let collected = progress.trove.get(treasure.id);
+    let text_color = if collected.is_some() { TEXT_COLOR } else { FADED_TEXT_COLOR };
+    let name_style = TextStyle { font_size: 28.0, color: text_color, font: fonts.text.clone() };
+    let info_style = TextStyle { font_size: 18.0, color: text_color, font: fonts.text.clone() };
+
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Px(240.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(6.0),
+                margin: UiRect::all(Val::Px(10.0)),
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            background_color: CARD_BACKGROUND.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            spawn_treasure_shape(parent, sprites, treasure, collected.is_some());
+
+            parent.spawn(TextBundle::from_section(treasure.name.clone(), name_style));
+            parent.spawn(TextBundle::from_section(
+                rarity_label(treasure.rarity),
+                TextStyle { color: rarity_color(treasure.rarity), ..info_style.clone() },
+            ));
+            let Some(collected) = collected else {
+                parent.spawn(TextBundle::from_section("Not found yet", info_style));
+                return;
+            };
+            parent.spawn(TextBundle::from_section(treasure.description.clone(), info_style.clone()));
+            parent.spawn(TextBundle::from_section(
+                format!("Collected x{}", collected.times_collected),
+                info_style.clone(),
+            ));
+            parent.spawn(TextBundle::from_section(format!("First found in {}", collected.first_found), info_style));
+        });
+}
+
+/// Lays out the treasure's atlas tiles in its authored shape, UI grids are filled from the top row down
+fn spawn_treasure_shape(parent: &mut ChildBuilder, sprites: &SpriteAssets, treasure: &TreasureInfo, discovered: bool) {
+    let Some(shape) = treasure.shape_grid().map(|shape| shape.flipped_y()) else {
+        warn!("treasure {} has a shape that does not match its size, not showing it", treasure.id);
+        return;
+    };
+    let tint = if discovered { Color::WHITE } else { UNDISCOVERED_TINT };
+
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                display: Display::Grid,
+                grid_template_columns: RepeatedGridTrack::px(shape.width() as u16, GALLERY_TILE_PX),
+                grid_template_rows: RepeatedGridTrack::px(shape.height() as u16, GALLERY_TILE_PX),
+                margin: UiRect::vertical(Val::Px(10.0)),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            for (_, tile) in shape.iter() {
+                if *tile == -1 {
+                    // keeps the gap in the shape
+                    parent.spawn(NodeBundle::default());
+                    continue;
+                }
+                parent.spawn(AtlasImageBundle {
+                    texture_atlas: sprites.treasures.clone(),
+                    texture_atlas_image: UiTextureAtlasImage { index: *tile as usize, ..default() },
+                    background_color: tint.into(),
+                    ..default()
+                });
+            }
+        });
+}
+
+pub(super) fn rarity_label(rarity: Rarity) -> &'static str {
+    match rarity {
+        Rarity::Common => "Common",
+        Rarity::Uncommon => "Uncommon",
+        Rarity::Rare => "Rare",
+        Rarity::Legendary => "Legendary",
+    }
+}
+
+pub(super) fn rarity_color(rarity: Rarity) -> Color {
+    match rarity {
+        Rarity::Common => Color::rgb(0.7, 0.7, 0.7),
+        Rarity::Uncommon => Color::rgb(0.4, 0.8, 0.4),
+        Rarity::Rare => Color::rgb(0.35, 0.55, 0.95),
+        Rarity::Legendary => Color::rgb(0.95, 0.7, 0.2),
+    }
+}
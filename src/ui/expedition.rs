@@ -7,18 +7,29 @@ use bevy_mod_picking::{
 
 use crate::{
     assets::{SpriteAssets, UiAssets},
-    expedition::{ExpeditionLeave, ExpeditionPersist, ExpeditionStatus},
-    stability::{Stability, StabilityDamage},
-    tools::{ui_tool_is_tool_type, ActiveTool, SwitchTool, ToolUnlocks},
-    AppState, SystemOrder, SPRITE_PX_X, SPRITE_PX_Y,
+    audio_events::UiSound,
+    data_read::TreasureDb,
+    expedition::{ActiveLevel, ExpeditionLeave, ExpeditionPersist, ExpeditionPhase, LevelChange},
+    mining::FootprintPreview,
+    progression::{Requirements, MAX_STARS},
+    run_stats::{ObjectiveState, RunOutcome, RunStats},
+    save::Progress,
+    stability::Stability,
+    tools::{ui_tool_is_tool_type, ActiveTool, Loadout, SwitchTool},
+    AppState, SystemOrder,
 };
 
-pub const TOOLS_Z: f32 = 71.0;
-pub const TOOLBAR_Z: f32 = 70.0;
+use super::{ButtonInteractions, HOVERED_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON, REFERENCE_RESOLUTION, STAR_COLOR};
+
+/// Size of a tool in the toolbar, in ui pixels
+const TOOL_SIZE: f32 = 48.0;
+/// Gap between the toolbar and the bottom of the window
+const TOOLBAR_BOTTOM: f32 = 16.0;
 // leave button colors
 const DARK_MAROON: Color = Color::rgb(122.0 / 255.0, 40.0 / 255.0, 73.0 / 255.0);
 const _RED: Color = Color::rgb(183.0 / 255.0, 65.0 / 255.0, 50.0 / 255.0);
 const LIGHT_RED: Color = Color::rgb(194.0 / 255.0, 55.0 / 255.0, 83.0 / 255.0);
+const MISSED_COLOR: Color = Color::rgb(0.8, 0.45, 0.45);
 
 #[derive(Component)]
 pub struct ExpeditionClearMenu;
@@ -26,8 +37,41 @@ pub struct ExpeditionClearMenu;
 #[derive(Component)]
 pub struct LeaveButton;
 
+/// Says how the expedition ended at the top of the clear menu
+#[derive(Component)]
+struct ResultTitle;
+
+/// Holds the lines of the run's stats, filled in once the expedition is over
 #[derive(Component)]
-pub struct StabilityText;
+struct ResultSummary;
+
+/// Buttons on the clear menu for going straight into another expedition
+#[derive(Component, Clone, Copy)]
+enum ClearMenuButton {
+    Retry,
+    NextLevel,
+}
+
+impl ClearMenuButton {
+    fn label(&self) -> &'static str {
+        match self {
+            ClearMenuButton::Retry => "Retry",
+            ClearMenuButton::NextLevel => "Next level",
+        }
+    }
+}
+
+/// Lists the level's objectives while mining, filled in once mining starts
+#[derive(Component)]
+struct ObjectiveTracker;
+
+/// One objective in the tracker, by its index in the run's objectives
+#[derive(Component)]
+struct ObjectiveLine(usize);
+
+/// Shows the stability the hovered click would cost next to the cursor
+#[derive(Component)]
+pub struct FootprintCostText;
 
 #[derive(Component, Copy, Clone)]
 pub enum UITool {
@@ -35,81 +79,126 @@ pub enum UITool {
     Pickaxe,
 }
 
+impl UITool {
+    /// Column of the tool in the tools tilesheet
+    fn atlas_index(&self) -> usize {
+        match self {
+            UITool::TinyHammer => 0,
+            UITool::Pickaxe => 1,
+        }
+    }
+}
+
+/// Holds a button for each tool in the loadout
+#[derive(Component)]
+struct Toolbar;
+
 pub struct ExpeditionUIPlugin;
 
 impl Plugin for ExpeditionUIPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(AppState::Expedition), init_expedition_ui)
-            .add_systems(Update, (expedition_buttons).run_if(in_state(AppState::Expedition)))
+            .add_systems(Update, (expedition_buttons, clear_menu_buttons).run_if(in_state(AppState::Expedition)))
             .add_systems(
                 Update,
-                (update_stability_text)
-                    .run_if(on_event::<StabilityDamage>())
+                (fill_toolbar)
+                    .run_if(in_state(AppState::Expedition))
+                    .run_if(resource_changed::<Loadout>())
                     .in_set(SystemOrder::Render)
                     .after(SystemOrder::Logic),
             )
             .add_systems(
                 Update,
                 (update_active_tool_sprite)
-                    .run_if(on_event::<SwitchTool>())
+                    .run_if(resource_changed::<ActiveTool>())
+                    .after(fill_toolbar)
                     .in_set(SystemOrder::Render)
                     .after(SystemOrder::Logic),
             )
-            .add_systems(Update, (reveal_clear_menu).run_if(in_state(AppState::Expedition)));
+            .add_systems(
+                Update,
+                (reveal_clear_menu).run_if(in_state(AppState::Expedition)).run_if(resource_changed::<RunStats>()),
+            )
+            .add_systems(
+                Update,
+                (update_next_level_button)
+                    .run_if(in_state(AppState::Expedition))
+                    .run_if(resource_changed::<Progress>().or_else(state_changed::<ExpeditionPhase>())),
+            )
+            .add_systems(OnEnter(ExpeditionPhase::Mining), fill_objective_tracker)
+            .add_systems(
+                Update,
+                (update_objective_tracker)
+                    .run_if(in_state(ExpeditionPhase::Mining))
+                    .run_if(resource_changed::<RunStats>()),
+            )
+            .add_systems(
+                Update,
+                (update_footprint_cost_text)
+                    .run_if(in_state(AppState::Expedition))
+                    .run_if(resource_changed::<FootprintPreview>())
+                    .in_set(SystemOrder::Render)
+                    .after(SystemOrder::Logic),
+            );
     }
 }
 
 // TODO: maybe change the starting x position to be centered based on how many tools are unlocked
-fn init_expedition_ui(
-    mut commands: Commands,
-    ui_assets: Res<UiAssets>,
-    sprites: Res<SpriteAssets>,
-    tool_unlocks: Res<ToolUnlocks>,
-    active_tool: Res<ActiveTool>,
-) {
+fn init_expedition_ui(mut commands: Commands, ui_assets: Res<UiAssets>) {
     info!("SETUP: creating ui elements for expedition");
-    let tools_unlocked = tool_unlocks.get_tools_for_ui();
-    let bar_y = -((3 * SPRITE_PX_Y) as f32);
-    let tool_y = -((3 * SPRITE_PX_Y - SPRITE_PX_Y / 2) as f32);
-    for (i, tool) in tools_unlocked.iter().enumerate() {
-        let x = ((i as u32 * 3) * SPRITE_PX_X) as f32 - (2 * SPRITE_PX_X) as f32;
-        commands.spawn((
-            SpriteBundle {
-                transform: Transform::from_xyz(x, bar_y, TOOLBAR_Z),
-                texture: ui_assets.tool_shadow.clone(),
-                ..default()
-            },
-            ExpeditionPersist,
-        ));
-
-        let tool_atlas_idx = if ui_tool_is_tool_type(tool, &active_tool.0) { i + 8 } else { i };
-        commands.spawn((
-            SpriteSheetBundle {
-                transform: Transform::from_xyz(x, tool_y, TOOLS_Z),
-                sprite: TextureAtlasSprite::new(tool_atlas_idx),
-                texture_atlas: sprites.tools.clone(),
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(TOOLBAR_BOTTOM),
+                width: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                column_gap: Val::Px(TOOL_SIZE),
                 ..default()
             },
-            On::<Pointer<Down>>::send_event::<SwitchTool>(),
-            *tool,
-            ExpeditionPersist,
-        ));
-    }
-
-    // Level Foreground fg
-    commands.spawn((
-        ImageBundle {
-            style: Style { width: Val::Percent(100.0), height: Val::Percent(100.0), ..Default::default() },
-            image: UiImage::new(ui_assets.level1fg.clone()),
-            z_index: ZIndex::Global(2),
-            ..Default::default()
+            z_index: ZIndex::Global(3),
+            ..default()
         },
         Pickable::IGNORE,
+        Toolbar,
         ExpeditionPersist,
     ));
 
-    let leave_style =
-        TextStyle { font: ui_assets.text.clone(), font_size: 32.0, color: Color::rgb_u8(255, 241, 169), ..default() };
+    // Level Foreground fg, kept at the size it was drawn for and scaled along with the rest of the ui
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                z_index: ZIndex::Global(2),
+                ..default()
+            },
+            Pickable::IGNORE,
+            ExpeditionPersist,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                ImageBundle {
+                    style: Style {
+                        width: Val::Px(REFERENCE_RESOLUTION.x),
+                        height: Val::Px(REFERENCE_RESOLUTION.y),
+                        flex_shrink: 0.0,
+                        ..default()
+                    },
+                    image: UiImage::new(ui_assets.level1fg.clone()),
+                    ..default()
+                },
+                Pickable::IGNORE,
+            ));
+        });
+
+    let leave_style = TextStyle { font: ui_assets.text.clone(), font_size: 32.0, color: Color::rgb_u8(255, 241, 169) };
     commands
         .spawn((
             ButtonBundle {
@@ -131,6 +220,7 @@ fn init_expedition_ui(
             },
             ExpeditionPersist,
             LeaveButton,
+            UiSound::default(),
         ))
         .with_children(|parent| {
             parent.spawn(TextBundle {
@@ -139,23 +229,38 @@ fn init_expedition_ui(
             });
         });
 
-    // DEBUG - stability text
     commands.spawn((
         TextBundle {
-            text: Text::from_sections([
-                TextSection::new(
-                    "Stability: ",
-                    TextStyle { font: ui_assets.text.clone(), font_size: 22.0, color: Color::rgb_u8(240, 10, 240) },
-                ),
-                TextSection::new(
-                    "",
-                    TextStyle { font: ui_assets.text.clone(), font_size: 22.0, color: Color::rgb_u8(255, 255, 255) },
-                ),
-            ]),
+            text: Text::from_section(
+                "",
+                TextStyle { font: ui_assets.text.clone(), font_size: 22.0, color: Color::rgb_u8(255, 255, 255) },
+            ),
+            style: Style { position_type: PositionType::Absolute, ..default() },
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(3),
+            ..default()
+        },
+        Pickable::IGNORE,
+        FootprintCostText,
+        ExpeditionPersist,
+    ));
+
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(16.0),
+                right: Val::Px(16.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::FlexEnd,
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
             z_index: ZIndex::Global(3),
             ..default()
         },
-        StabilityText,
+        Pickable::IGNORE,
+        ObjectiveTracker,
         ExpeditionPersist,
     ));
 
@@ -182,27 +287,133 @@ fn init_expedition_ui(
             ExpeditionPersist,
         ))
         .with_children(|parent| {
-            parent.spawn(TextBundle {
-                style: Style {
-                    margin: UiRect::new(Val::Percent(10.0), Val::ZERO, Val::Percent(10.0), Val::ZERO),
+            parent.spawn((
+                TextBundle {
+                    style: Style {
+                        margin: UiRect::new(Val::Percent(10.0), Val::ZERO, Val::Percent(8.0), Val::ZERO),
+                        ..default()
+                    },
+                    text: Text::from_section("", cleared_style.clone()),
                     ..default()
                 },
-                text: Text::from_section("Expedition Cleared!", cleared_style.clone()),
-                ..default()
-            });
+                ResultTitle,
+            ));
 
-            parent.spawn(TextBundle {
-                style: Style {
-                    margin: UiRect::new(Val::Percent(10.0), Val::ZERO, Val::Percent(10.0), Val::ZERO),
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        margin: UiRect::new(Val::Percent(10.0), Val::Percent(10.0), Val::Percent(4.0), Val::ZERO),
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(4.0),
+                        ..default()
+                    },
                     ..default()
                 },
-                text: Text::from_section("Total Treasures Found ##", cleared_style.clone()),
-                ..default()
-            });
+                ResultSummary,
+            ));
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        margin: UiRect::new(Val::Percent(10.0), Val::Percent(10.0), Val::Auto, Val::Percent(10.0)),
+                        justify_content: JustifyContent::SpaceBetween,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for button in [ClearMenuButton::Retry, ClearMenuButton::NextLevel] {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        padding: UiRect::axes(Val::Px(15.0), Val::Px(5.0)),
+                                        border: UiRect::all(Val::Px(3.0)),
+                                        ..default()
+                                    },
+                                    border_color: BorderColor(Color::BLACK),
+                                    background_color: NORMAL_BUTTON.into(),
+                                    ..default()
+                                },
+                                button,
+                                UiSound::default(),
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(button.label(), cleared_style.clone()));
+                            });
+                    }
+                });
         });
 }
 
-fn update_active_tool_sprite(mut q_ui_tools: Query<(&mut TextureAtlasSprite, &UITool)>, active_tool: Res<ActiveTool>) {
+/// Puts a button in the toolbar for each tool in the loadout, redone whenever the loadout is changed
+fn fill_toolbar(
+    mut commands: Commands,
+    q_toolbar: Query<Entity, With<Toolbar>>,
+    ui_assets: Res<UiAssets>,
+    sprites: Res<SpriteAssets>,
+    loadout: Res<Loadout>,
+    active_tool: Res<ActiveTool>,
+) {
+    let Ok(toolbar) = q_toolbar.get_single() else {
+        return;
+    };
+    commands.entity(toolbar).despawn_descendants().with_children(|parent| {
+        for tool in loadout.tools_for_ui() {
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Px(TOOL_SIZE),
+                            height: Val::Px(TOOL_SIZE + TOOL_SIZE / 6.0),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    Pickable::IGNORE,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        ImageBundle {
+                            style: Style {
+                                position_type: PositionType::Absolute,
+                                bottom: Val::Px(0.0),
+                                width: Val::Percent(100.0),
+                                ..default()
+                            },
+                            image: UiImage::new(ui_assets.tool_shadow.clone()),
+                            ..default()
+                        },
+                        Pickable::IGNORE,
+                    ));
+
+                    let tool_atlas_idx = if ui_tool_is_tool_type(&tool, &active_tool.0) {
+                        tool.atlas_index() + 8
+                    } else {
+                        tool.atlas_index()
+                    };
+                    parent.spawn((
+                        AtlasImageBundle {
+                            style: Style {
+                                position_type: PositionType::Absolute,
+                                top: Val::Px(0.0),
+                                width: Val::Px(TOOL_SIZE),
+                                height: Val::Px(TOOL_SIZE),
+                                ..default()
+                            },
+                            texture_atlas: sprites.tools.clone(),
+                            texture_atlas_image: UiTextureAtlasImage { index: tool_atlas_idx, ..default() },
+                            ..default()
+                        },
+                        On::<Pointer<Down>>::send_event::<SwitchTool>(),
+                        tool,
+                    ));
+                });
+        }
+    });
+}
+
+fn update_active_tool_sprite(mut q_ui_tools: Query<(&mut UiTextureAtlasImage, &UITool)>, active_tool: Res<ActiveTool>) {
     // NOTE: this 8 relates to the columns in the tools tilesheets, by addding
     // the width we get the idx of the highlighted variant of the tool sprite
     for (mut ui_tool_sprite, ui_tool) in q_ui_tools.iter_mut() {
@@ -222,11 +433,8 @@ fn update_active_tool_sprite(mut q_ui_tools: Query<(&mut TextureAtlasSprite, &UI
     }
 }
 
-fn expedition_buttons(
-    mut q_leave_button: Query<(&Interaction, &mut BackgroundColor), (Changed<Interaction>, With<LeaveButton>)>,
-    mut ev_leave: EventWriter<ExpeditionLeave>,
-) {
-    for (interaction, mut bg_color) in &mut q_leave_button {
+fn expedition_buttons(mut q_leave_button: ButtonInteractions<LeaveButton>, mut ev_leave: EventWriter<ExpeditionLeave>) {
+    for (interaction, mut bg_color, _) in &mut q_leave_button {
         match *interaction {
             Interaction::Pressed => {
                 ev_leave.send_default();
@@ -242,32 +450,189 @@ fn expedition_buttons(
     }
 }
 
-fn update_stability_text(mut q_stability_text: Query<&mut Text, With<StabilityText>>, stability: Res<Stability>) {
-    let mut stability_text = match q_stability_text.get_single_mut() {
-        Ok(t) => t,
-        Err(e) => {
-            warn!("No stability text found so cannot update it., {}", e);
-            return;
+/// Both pick a new seed, the layout of the level just played is already known
+fn clear_menu_buttons(
+    mut q_interaction: ButtonInteractions<ClearMenuButton>,
+    active_level: Res<ActiveLevel>,
+    mut ev_level_change: EventWriter<LevelChange>,
+) {
+    for (interaction, mut color, button) in &mut q_interaction {
+        match *interaction {
+            Interaction::Pressed => {
+                *color = PRESSED_BUTTON.into();
+                let level_idx = match button {
+                    ClearMenuButton::Retry => active_level.level_idx,
+                    ClearMenuButton::NextLevel => active_level.level_idx + 1,
+                };
+                ev_level_change.send(LevelChange::new(active_level.area.clone(), level_idx));
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
         }
-    };
+    }
+}
 
-    stability_text.sections[1].value = stability.remaining.to_string();
+/// Only offers the next level when there is one in the area and it has been unlocked
+fn update_next_level_button(
+    mut q_button: Query<(&mut Style, &ClearMenuButton)>,
+    active_level: Res<ActiveLevel>,
+    requirements: Requirements,
+) {
+    let next_idx = active_level.level_idx + 1;
+    let ctx = requirements.ctx();
+    let exists = ctx.level_db.get(&active_level.area.to_string()).is_some_and(|area| next_idx < area.levels.len());
+    let unlocked = exists && ctx.is_level_unlocked(&active_level.area, next_idx);
+    for (mut style, button) in q_button.iter_mut() {
+        if matches!(button, ClearMenuButton::NextLevel) {
+            style.display = if unlocked { Display::Flex } else { Display::None };
+        }
+    }
 }
 
+/// Shows the results of the run once it is over, whether it was cleared or the cave came down
 fn reveal_clear_menu(
+    mut commands: Commands,
     mut q_clear_menu: Query<&mut Visibility, With<ExpeditionClearMenu>>,
-    expedition_status: Res<ExpeditionStatus>,
+    mut q_title: Query<&mut Text, With<ResultTitle>>,
+    q_summary: Query<Entity, With<ResultSummary>>,
+    stats: Res<RunStats>,
+    tdb: Res<TreasureDb>,
+    ui_assets: Res<UiAssets>,
 ) {
-    if !(expedition_status.is_changed() && matches!(*expedition_status, ExpeditionStatus::Cleared)) {
+    let title = match stats.outcome {
+        RunOutcome::InProgress => return,
+        RunOutcome::Cleared => "Expedition Cleared!",
+        RunOutcome::LeftEarly => "Left Early",
+        RunOutcome::Failed => "The Cave Came Down!",
+    };
+    let Ok(mut clear_menu) = q_clear_menu.get_single_mut() else {
         return;
+    };
+    info!("showing the results of the expedition");
+    *clear_menu = Visibility::Visible;
+    if let Ok(mut text) = q_title.get_single_mut() {
+        text.sections[0].value = title.to_string();
     }
+    let Ok(summary) = q_summary.get_single() else {
+        return;
+    };
 
-    let mut clear_menu = q_clear_menu.single_mut();
-    match *clear_menu {
-        Visibility::Hidden => {
-            info!("Reading an expedition clear, first time");
-            *clear_menu = Visibility::Visible;
+    let text_style = TextStyle { font: ui_assets.text.clone(), font_size: 20.0, color: Color::rgb_u8(255, 241, 169) };
+    let treasure_names = |ids: &[u32]| {
+        ids.iter().filter_map(|id| tdb.get_by_id(*id)).map(|t| t.name.clone()).collect::<Vec<_>>().join(", ")
+    };
+    let tool_uses =
+        stats.tools.iter().map(|tool| format!("{} x{}", tool.tool.name(), tool.actions)).collect::<Vec<_>>();
+    let mut lines = vec![
+        (format!("{}/{} stars", stats.stars, MAX_STARS), STAR_COLOR),
+        (format!("Time: {:.0}s", stats.seconds), text_style.color),
+        (format!("Swings: {} ({})", stats.actions(), tool_uses.join(", ")), text_style.color),
+        (format!("Rocks broken: {}", stats.tiles_broken), text_style.color),
+        (
+            format!(
+                "Stability: {} spent, {} of {} left",
+                stats.stability_spent,
+                stats.stability_remaining.max(0),
+                stats.stability_start
+            ),
+            text_style.color,
+        ),
+        (
+            format!("Found {} of {} treasures", stats.found.len(), stats.found.len() + stats.missed.len()),
+            text_style.color,
+        ),
+    ];
+    if !stats.missed.is_empty() {
+        lines.push((format!("Missed: {}", treasure_names(&stats.missed)), MISSED_COLOR));
+    }
+    for progress in stats.objectives.iter() {
+        let result = if progress.state == ObjectiveState::Met { "Done" } else { "Not done" };
+        lines.push((format!("{}: {}", result, progress.objective.describe()), objective_color(progress.state)));
+    }
+    match stats.outcome {
+        RunOutcome::Cleared | RunOutcome::LeftEarly if !stats.found.is_empty() => {
+            lines.push((format!("Brought back: {}", treasure_names(&stats.found)), text_style.color));
+            lines.push((format!("Worth {} coins", stats.reward_value), STAR_COLOR));
         }
-        _ => {}
+        _ => lines.push(("Nothing could be brought back".to_string(), MISSED_COLOR)),
     }
+
+    commands.entity(summary).despawn_descendants().with_children(|parent| {
+        for (line, color) in lines {
+            parent.spawn(TextBundle::from_section(line, TextStyle { color, ..text_style.clone() }));
+        }
+    });
+}
+
+fn objective_color(state: ObjectiveState) -> Color {
+    match state {
+        ObjectiveState::OnTrack => Color::rgb_u8(255, 241, 169),
+        ObjectiveState::Met => STAR_COLOR,
+        ObjectiveState::Failed => MISSED_COLOR,
+    }
+}
+
+fn fill_objective_tracker(
+    mut commands: Commands,
+    q_tracker: Query<Entity, With<ObjectiveTracker>>,
+    stats: Res<RunStats>,
+    ui_assets: Res<UiAssets>,
+) {
+    let Ok(tracker) = q_tracker.get_single() else {
+        return;
+    };
+    let text_style = TextStyle { font: ui_assets.text.clone(), font_size: 18.0, color: Color::WHITE };
+    commands.entity(tracker).despawn_descendants().with_children(|parent| {
+        for (idx, progress) in stats.objectives.iter().enumerate() {
+            parent.spawn((
+                TextBundle::from_section(
+                    progress.objective.describe(),
+                    TextStyle { color: objective_color(progress.state), ..text_style.clone() },
+                ),
+                Pickable::IGNORE,
+                ObjectiveLine(idx),
+            ));
+        }
+    });
+}
+
+fn update_objective_tracker(mut q_lines: Query<(&mut Text, &ObjectiveLine)>, stats: Res<RunStats>) {
+    for (mut text, line) in q_lines.iter_mut() {
+        let Some(progress) = stats.objectives.get(line.0) else {
+            continue;
+        };
+        let color = objective_color(progress.state);
+        // the stats change every frame while mining, only touch the text when the objective does
+        if text.sections[0].style.color != color {
+            text.sections[0].style.color = color;
+        }
+    }
+}
+
+fn update_footprint_cost_text(
+    mut q_cost_text: Query<(&mut Text, &mut Style, &mut Visibility), With<FootprintCostText>>,
+    preview: Res<FootprintPreview>,
+    stability: Res<Stability>,
+    ui_scale: Res<UiScale>,
+) {
+    let Ok((mut text, mut style, mut vis)) = q_cost_text.get_single_mut() else {
+        return;
+    };
+    let (Some(cost), Some(cursor)) = (preview.cost, preview.cursor) else {
+        *vis = Visibility::Hidden;
+        return;
+    };
+
+    *vis = Visibility::Visible;
+    // the cursor is in window pixels but ui pixels get scaled up with the window
+    let cursor = cursor / ui_scale.0 as f32;
+    style.left = Val::Px(cursor.x + 16.0);
+    style.top = Val::Px(cursor.y - 24.0);
+    text.sections[0].value = format!("-{}", cost);
+    // warn the player when the swing would use up the last of the stability
+    text.sections[0].style.color = if cost as i32 >= stability.remaining { LIGHT_RED } else { Color::WHITE };
 }
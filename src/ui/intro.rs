@@ -0,0 +1,231 @@
+use bevy::{prelude::*, ui::FocusPolicy};
+
+use super::{ButtonInteractions, HOVERED_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON, STAR_COLOR, TEXT_COLOR};
+use crate::{
+    assets::UiAssets,
+    audio_events::UiSound,
+    expedition::{ExpeditionPhase, PhaseChange},
+    run_stats::RunStats,
+    stability::Stability,
+    tools::{Loadout, ToolType},
+    treasures::Treasure,
+};
+
+const OVERLAY_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.6);
+const PANEL_COLOR: Color = Color::rgb(0.1, 0.1, 0.12);
+
+pub struct IntroUIPlugin;
+
+impl Plugin for IntroUIPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(ExpeditionPhase::Intro), setup_intro)
+            .add_systems(OnExit(ExpeditionPhase::Intro), cleanup_intro)
+            .add_systems(Update, (start_button, loadout_buttons).run_if(in_state(ExpeditionPhase::Intro)))
+            .add_systems(
+                Update,
+                (update_loadout_buttons)
+                    .after(loadout_buttons)
+                    .run_if(in_state(ExpeditionPhase::Intro))
+                    .run_if(resource_changed::<Loadout>()),
+            );
+    }
+}
+
+#[derive(Component)]
+struct IntroUIMaster;
+
+#[derive(Component)]
+struct StartButton;
+
+/// Puts the tool in or takes it out of the loadout
+#[derive(Component)]
+struct LoadoutButton(ToolType);
+
+fn start_button(mut q_interaction: ButtonInteractions<StartButton>, mut ev_phase: EventWriter<PhaseChange>) {
+    for (interaction, mut color, _) in &mut q_interaction {
+        match *interaction {
+            Interaction::Pressed => {
+                *color = PRESSED_BUTTON.into();
+                ev_phase.send(PhaseChange(ExpeditionPhase::Mining));
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+fn loadout_buttons(mut q_interaction: ButtonInteractions<LoadoutButton>, mut loadout: ResMut<Loadout>) {
+    for (interaction, mut color, button) in &mut q_interaction {
+        match *interaction {
+            Interaction::Pressed => {
+                *color = PRESSED_BUTTON.into();
+                loadout.toggle(button.0);
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+fn picked_border(loadout: &Loadout, tool: &ToolType) -> BorderColor {
+    BorderColor(if loadout.contains(tool) { STAR_COLOR } else { Color::BLACK })
+}
+
+fn update_loadout_buttons(mut q_buttons: Query<(&mut BorderColor, &LoadoutButton)>, loadout: Res<Loadout>) {
+    for (mut border, button) in q_buttons.iter_mut() {
+        *border = picked_border(&loadout, &button.0);
+    }
+}
+
+fn setup_intro(
+    mut commands: Commands,
+    fonts: Res<UiAssets>,
+    stats: Res<RunStats>,
+    stability: Res<Stability>,
+    loadout: Res<Loadout>,
+    q_treasures: Query<&Treasure>,
+) {
+    debug!("setting up ui for the level intro");
+    let title_style = TextStyle { font_size: 40.0, color: TEXT_COLOR, font: fonts.text.clone() };
+    let text_style = TextStyle { font_size: 20.0, color: TEXT_COLOR, font: fonts.text.clone() };
+    let hidden = q_treasures.iter().count();
+    let lines = [
+        format!("{} - {}", stats.area, stats.level),
+        format!("{} treasure{} hidden in the rock", hidden, if hidden == 1 { " is" } else { "s are" }),
+        format!("Stability: {} ({:?})", stability.start, stability.profile),
+    ];
+    let loadout_hint = match loadout.size() {
+        Some(1) => "Pick a tool to bring".to_string(),
+        Some(size) => format!("Pick up to {} tools to bring", size),
+        None => "Pick the tools to bring".to_string(),
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: OVERLAY_COLOR.into(),
+                focus_policy: FocusPolicy::Block,
+                // over the rest of the expedition ui
+                z_index: ZIndex::Global(4),
+                ..default()
+            },
+            IntroUIMaster,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        row_gap: Val::Px(10.0),
+                        padding: UiRect::all(Val::Px(20.0)),
+                        min_width: Val::Px(300.0),
+                        ..default()
+                    },
+                    background_color: PANEL_COLOR.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Expedition", title_style.clone()));
+                    for line in lines {
+                        parent.spawn(TextBundle::from_section(line, text_style.clone()));
+                    }
+                    parent.spawn(TextBundle::from_section(
+                        loadout_hint,
+                        TextStyle { font_size: 28.0, ..text_style.clone() },
+                    ));
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                column_gap: Val::Px(10.0),
+                                flex_wrap: FlexWrap::Wrap,
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            for tool in loadout.available() {
+                                let label = match loadout.is_required(tool) {
+                                    true => format!("{} (required)", tool.name()),
+                                    false => tool.name(),
+                                };
+                                parent
+                                    .spawn((
+                                        ButtonBundle {
+                                            style: Style {
+                                                padding: UiRect::axes(Val::Px(10.0), Val::Px(5.0)),
+                                                border: UiRect::all(Val::Px(3.0)),
+                                                ..default()
+                                            },
+                                            border_color: picked_border(&loadout, tool),
+                                            background_color: NORMAL_BUTTON.into(),
+                                            ..default()
+                                        },
+                                        LoadoutButton(*tool),
+                                        UiSound::default(),
+                                    ))
+                                    .with_children(|parent| {
+                                        parent.spawn(TextBundle::from_section(label, text_style.clone()));
+                                    });
+                            }
+                        });
+                    if !stats.objectives.is_empty() {
+                        parent.spawn(TextBundle::from_section(
+                            "Objectives",
+                            TextStyle { font_size: 28.0, ..text_style.clone() },
+                        ));
+                        for progress in stats.objectives.iter() {
+                            parent.spawn(TextBundle::from_section(
+                                progress.objective.describe(),
+                                TextStyle { color: STAR_COLOR, ..text_style.clone() },
+                            ));
+                        }
+                    }
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    padding: UiRect::axes(Val::Px(15.0), Val::Px(5.0)),
+                                    border: UiRect::all(Val::Px(3.0)),
+                                    margin: UiRect::top(Val::Px(10.0)),
+                                    ..default()
+                                },
+                                border_color: BorderColor(Color::BLACK),
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            StartButton,
+                            UiSound::default(),
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(
+                                "Start digging",
+                                TextStyle { font_size: 28.0, ..text_style.clone() },
+                            ));
+                        });
+                });
+        });
+}
+
+fn cleanup_intro(mut commands: Commands, q_root: Query<Entity, With<IntroUIMaster>>) {
+    for e in q_root.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
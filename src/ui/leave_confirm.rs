@@ -0,0 +1,150 @@
+use bevy::{prelude::*, ui::FocusPolicy};
+
+use super::{ButtonInteractions, HOVERED_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON, TEXT_COLOR};
+use crate::{
+    assets::UiAssets,
+    audio_events::UiSound,
+    expedition::{ExpeditionPhase, LeaveConfirm, PhaseChange},
+    treasures::Treasure,
+};
+
+const OVERLAY_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.6);
+const PANEL_COLOR: Color = Color::rgb(0.1, 0.1, 0.12);
+
+pub struct LeaveConfirmUIPlugin;
+
+impl Plugin for LeaveConfirmUIPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(LeaveConfirm::Open), setup_leave_confirm)
+            .add_systems(OnExit(LeaveConfirm::Open), cleanup_leave_confirm)
+            .add_systems(Update, leave_confirm_buttons.run_if(in_state(LeaveConfirm::Open)));
+    }
+}
+
+#[derive(Component)]
+struct LeaveConfirmUIMaster;
+
+#[derive(Component, Clone, Copy)]
+enum LeaveConfirmButton {
+    Leave,
+    Stay,
+}
+
+impl LeaveConfirmButton {
+    fn label(&self) -> &'static str {
+        match self {
+            LeaveConfirmButton::Leave => "Leave",
+            LeaveConfirmButton::Stay => "Keep digging",
+        }
+    }
+}
+
+fn leave_confirm_buttons(
+    mut q_interaction: ButtonInteractions<LeaveConfirmButton>,
+    mut ev_phase: EventWriter<PhaseChange>,
+    mut next_confirm: ResMut<NextState<LeaveConfirm>>,
+) {
+    for (interaction, mut color, button) in &mut q_interaction {
+        match *interaction {
+            Interaction::Pressed => {
+                *color = PRESSED_BUTTON.into();
+                if matches!(button, LeaveConfirmButton::Leave) {
+                    ev_phase.send(PhaseChange(ExpeditionPhase::LeftEarly));
+                }
+                next_confirm.set(LeaveConfirm::Closed);
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+fn setup_leave_confirm(mut commands: Commands, fonts: Res<UiAssets>, q_treasures: Query<&Treasure>) {
+    debug!("setting up ui for confirming leaving early");
+    let title_style = TextStyle { font_size: 36.0, color: TEXT_COLOR, font: fonts.text.clone() };
+    let text_style = TextStyle { font_size: 20.0, color: TEXT_COLOR, font: fonts.text.clone() };
+    let left = q_treasures.iter().filter(|treasure| !treasure.is_discovered).count();
+    let warning = format!(
+        "{} treasure{} still in the rock will be lost.\nOnly what has been dug up is kept.",
+        left,
+        if left == 1 { " is" } else { "s are" }
+    );
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: OVERLAY_COLOR.into(),
+                // keeps clicks from reaching the leave button again
+                focus_policy: FocusPolicy::Block,
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+            LeaveConfirmUIMaster,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        row_gap: Val::Px(12.0),
+                        padding: UiRect::all(Val::Px(20.0)),
+                        ..default()
+                    },
+                    background_color: PANEL_COLOR.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Leave early?", title_style.clone()));
+                    parent.spawn(
+                        TextBundle::from_section(warning, text_style.clone())
+                            .with_text_alignment(TextAlignment::Center),
+                    );
+                    parent
+                        .spawn(NodeBundle { style: Style { column_gap: Val::Px(20.0), ..default() }, ..default() })
+                        .with_children(|parent| {
+                            for button in [LeaveConfirmButton::Stay, LeaveConfirmButton::Leave] {
+                                parent
+                                    .spawn((
+                                        ButtonBundle {
+                                            style: Style {
+                                                padding: UiRect::axes(Val::Px(15.0), Val::Px(5.0)),
+                                                border: UiRect::all(Val::Px(3.0)),
+                                                ..default()
+                                            },
+                                            border_color: BorderColor(Color::BLACK),
+                                            background_color: NORMAL_BUTTON.into(),
+                                            ..default()
+                                        },
+                                        button,
+                                        UiSound::default(),
+                                    ))
+                                    .with_children(|parent| {
+                                        parent.spawn(TextBundle::from_section(
+                                            button.label(),
+                                            TextStyle { font_size: 28.0, ..text_style.clone() },
+                                        ));
+                                    });
+                            }
+                        });
+                });
+        });
+}
+
+fn cleanup_leave_confirm(mut commands: Commands, q_root: Query<Entity, With<LeaveConfirmUIMaster>>) {
+    for e in q_root.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
@@ -1,12 +1,30 @@
+mod collection;
 mod expedition;
+mod intro;
+mod leave_confirm;
+mod pause;
+mod settings;
+mod shop;
+mod stability_meter;
 
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    window::{PrimaryWindow, WindowResized},
+};
 
-use self::expedition::ExpeditionUIPlugin;
+use self::{
+    collection::CollectionUIPlugin, expedition::ExpeditionUIPlugin, intro::IntroUIPlugin,
+    leave_confirm::LeaveConfirmUIPlugin, pause::PauseUIPlugin, settings::SettingsUIPlugin, shop::ShopUIPlugin,
+    stability_meter::StabilityMeterPlugin,
+};
 use crate::{
     assets::UiAssets,
-    data_read::LEVEL_DB,
+    audio_events::UiSound,
+    data_read::{LevelDb, TreasureDb},
     expedition::{in_area_state, Area, LevelChange},
+    progression::{RequirementCtx, MAX_STARS},
+    save::Progress,
+    settings::SettingsMenu,
     AppState,
 };
 
@@ -17,16 +35,71 @@ pub mod prelude {
 pub struct UIPlugins;
 impl Plugin for UIPlugins {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(AppState::AreaViewer { curr_area: Area::TheCaves }), setup_areaviewer)
-            .add_systems(Update, button_system.run_if(in_area_state))
-            .add_systems(OnExit(AppState::AreaViewer { curr_area: Area::TheCaves }), cleanup)
-            .add_plugins(ExpeditionUIPlugin);
+        for curr_area in Area::ALL {
+            app.add_systems(OnEnter(AppState::AreaViewer { curr_area: curr_area.clone() }), setup_areaviewer)
+                .add_systems(OnExit(AppState::AreaViewer { curr_area }), cleanup);
+        }
+        app.add_systems(Startup, scale_ui_to_window)
+            .add_systems(Update, scale_ui_to_window.run_if(on_event::<WindowResized>()))
+            .add_systems(Update, (button_system, area_tab_buttons).run_if(in_area_state))
+            .add_systems(Update, menu_buttons)
+            .add_systems(
+                Update,
+                (cleanup, setup_areaviewer)
+                    .chain()
+                    .run_if(in_area_state)
+                    .run_if(resource_changed::<LevelDb>().or_else(resource_changed::<TreasureDb>())),
+            )
+            .add_plugins((
+                ExpeditionUIPlugin,
+                StabilityMeterPlugin,
+                CollectionUIPlugin,
+                ShopUIPlugin,
+                SettingsUIPlugin,
+                PauseUIPlugin,
+                LeaveConfirmUIPlugin,
+                IntroUIPlugin,
+            ));
     }
 }
 
+/// Window size the ui was laid out for, it is scaled up or down from this to fit the window
+pub(crate) const REFERENCE_RESOLUTION: Vec2 = Vec2::new(1280.0, 720.0);
+
 const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
 const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
 const PRESSED_BUTTON: Color = Color::rgb(0.35, 0.75, 0.35);
+const LOCKED_BUTTON: Color = Color::rgb(0.08, 0.08, 0.08);
+const TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+const LOCKED_TEXT: Color = Color::rgb(0.45, 0.45, 0.45);
+const STAR_COLOR: Color = Color::rgb(1.0, 0.84, 0.3);
+
+/// Buttons marked with `B` whose interaction changed this frame
+type ButtonInteractions<'w, 's, B> = Query<
+    'w,
+    's,
+    (&'static Interaction, &'static mut BackgroundColor, &'static B),
+    (Changed<Interaction>, With<Button>),
+>;
+
+/// Level buttons also change their border when hovered
+type LevelButtonInteractions<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Interaction, &'static mut BackgroundColor, &'static mut BorderColor, &'static ButtonLevelData),
+    (Changed<Interaction>, With<Button>),
+>;
+
+/// Scales the ui so it takes up the same part of the window at any resolution
+fn scale_ui_to_window(q_window: Query<&Window, With<PrimaryWindow>>, mut ui_scale: ResMut<UiScale>) {
+    let Ok(window) = q_window.get_single() else {
+        return;
+    };
+    let scale = (Vec2::new(window.width(), window.height()) / REFERENCE_RESOLUTION).min_element();
+    if scale > 0.0 {
+        ui_scale.0 = scale as f64;
+    }
+}
 
 #[derive(Component)]
 struct ButtonLevelData {
@@ -34,18 +107,19 @@ struct ButtonLevelData {
 }
 
 fn button_system(
-    mut q_interaction: Query<
-        (&Interaction, &mut BackgroundColor, &mut BorderColor, &ButtonLevelData),
-        (Changed<Interaction>, With<Button>),
-    >,
+    mut q_interaction: LevelButtonInteractions,
     mut ev: EventWriter<LevelChange>,
+    app_state: Res<State<AppState>>,
 ) {
+    let AppState::AreaViewer { curr_area } = app_state.get() else {
+        return;
+    };
     for (interaction, mut color, mut border_color, bld) in &mut q_interaction {
         match *interaction {
             Interaction::Pressed => {
                 *color = PRESSED_BUTTON.into();
                 border_color.0 = Color::RED;
-                ev.send(LevelChange { area: Area::TheCaves, level_idx: bld.level_idx });
+                ev.send(LevelChange::new(curr_area.clone(), bld.level_idx));
             }
             Interaction::Hovered => {
                 *color = HOVERED_BUTTON.into();
@@ -59,22 +133,129 @@ fn button_system(
     }
 }
 
+/// Buttons for moving between the area viewer and the screens that hang off of it
+#[derive(Component, Clone, Copy)]
+enum MenuButton {
+    Collection,
+    Shop,
+    Settings,
+    Back,
+}
+
+impl MenuButton {
+    fn label(&self) -> &'static str {
+        match self {
+            MenuButton::Collection => "Collection",
+            MenuButton::Shop => "Shop",
+            MenuButton::Settings => "Settings",
+            MenuButton::Back => "Back",
+        }
+    }
+}
+
+fn menu_buttons(
+    mut q_interaction: ButtonInteractions<MenuButton>,
+    app_state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut next_settings: ResMut<NextState<SettingsMenu>>,
+) {
+    for (interaction, mut color, button) in &mut q_interaction {
+        match *interaction {
+            Interaction::Pressed => {
+                *color = PRESSED_BUTTON.into();
+                if matches!(button, MenuButton::Settings) {
+                    next_settings.set(SettingsMenu::Open);
+                    continue;
+                }
+                let curr_area = match app_state.get() {
+                    AppState::AreaViewer { curr_area }
+                    | AppState::Collection { curr_area }
+                    | AppState::Shop { curr_area } => curr_area.clone(),
+                    _ => continue,
+                };
+                next_state.set(match button {
+                    MenuButton::Collection => AppState::Collection { curr_area },
+                    MenuButton::Shop => AppState::Shop { curr_area },
+                    MenuButton::Back | MenuButton::Settings => AppState::AreaViewer { curr_area },
+                });
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+/// Spawns the menu buttons stacked in the top right corner
+fn spawn_menu_buttons(parent: &mut ChildBuilder, fonts: &UiAssets, buttons: &[MenuButton]) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                right: Val::Px(20.0),
+                top: Val::Px(20.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            for button in buttons {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                padding: UiRect::axes(Val::Px(15.0), Val::Px(5.0)),
+                                border: UiRect::all(Val::Px(3.0)),
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            border_color: BorderColor(Color::BLACK),
+                            background_color: NORMAL_BUTTON.into(),
+                            ..default()
+                        },
+                        *button,
+                        UiSound::default(),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            button.label(),
+                            TextStyle { font_size: 28.0, color: TEXT_COLOR, font: fonts.text.clone() },
+                        ));
+                    });
+            }
+        });
+}
+
 #[derive(Component)]
 pub struct StateUIMaster;
 
-fn setup_areaviewer(mut commands: Commands, fonts: Res<UiAssets>) {
+fn setup_areaviewer(
+    mut commands: Commands,
+    fonts: Res<UiAssets>,
+    info: Res<LevelDb>,
+    tdb: Res<TreasureDb>,
+    progress: Res<Progress>,
+    app_state: Res<State<AppState>>,
+) {
     debug!("setting up ui for area viewer");
-    let Some(info) = LEVEL_DB.get() else {
+    let AppState::AreaViewer { curr_area } = app_state.get() else {
         return;
     };
+    let ctx = RequirementCtx { progress: &progress, level_db: &info, treasure_db: &tdb };
+    let requirement_style = TextStyle { font_size: 18.0, color: LOCKED_TEXT, font: fonts.text.clone() };
+
     commands
         .spawn((
             NodeBundle {
                 style: Style {
                     width: Val::Percent(100.0),
                     height: Val::Percent(100.0),
-                    align_items: AlignItems::Center,
-                    justify_content: JustifyContent::SpaceAround,
+                    flex_direction: FlexDirection::Column,
                     ..default()
                 },
                 ..default()
@@ -82,38 +263,156 @@ fn setup_areaviewer(mut commands: Commands, fonts: Res<UiAssets>) {
             StateUIMaster,
         ))
         .with_children(|parent| {
-            debug!("does info work");
-            let Some(area) = info.get(&Area::TheCaves.to_string()) else {
+            spawn_menu_buttons(parent, &fonts, &[MenuButton::Collection, MenuButton::Shop, MenuButton::Settings]);
+
+            // tabs for switching between areas
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(20.0),
+                        padding: UiRect::all(Val::Px(20.0)),
+                        align_items: AlignItems::FlexStart,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for area in Area::ALL {
+                        let unmet = ctx.unmet(&area, None);
+                        let requirements = unmet.iter().map(|req| ctx.describe(req, &area, None)).collect::<Vec<_>>();
+                        let is_current = area == *curr_area;
+                        spawn_area_tab(parent, &fonts, &area, is_current, &requirements, &requirement_style);
+                    }
+                });
+
+            let Some(area) = info.get(&curr_area.to_string()) else {
                 return;
             };
-            for (idx, level) in area.levels.iter().enumerate() {
-                parent
-                    .spawn((
-                        ButtonBundle {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        flex_grow: 1.0,
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::SpaceAround,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (idx, level) in area.levels.iter().enumerate() {
+                        let unmet = ctx.unmet(curr_area, Some(idx));
+                        let is_locked = !ctx.is_level_unlocked(curr_area, idx);
+                        // locked levels keep the look of a button but can not be pressed
+                        let mut level_ui = parent.spawn(NodeBundle {
                             style: Style {
                                 width: Val::Auto,
-                                height: Val::Percent(15.0),
+                                min_height: Val::Percent(15.0),
                                 border: UiRect::all(Val::Px(5.0)),
+                                padding: UiRect::all(Val::Px(10.0)),
+                                flex_direction: FlexDirection::Column,
                                 justify_content: JustifyContent::Center,
                                 align_items: AlignItems::Center,
                                 ..default()
                             },
                             border_color: BorderColor(Color::BLACK),
-                            background_color: NORMAL_BUTTON.into(),
+                            background_color: if is_locked { LOCKED_BUTTON } else { NORMAL_BUTTON }.into(),
                             ..default()
-                        },
-                        ButtonLevelData { level_idx: idx },
-                    ))
-                    .with_children(|parent| {
-                        parent.spawn(TextBundle::from_section(
-                            format!("{}", level.name),
-                            TextStyle { font_size: 40.0, color: Color::rgb(0.9, 0.9, 0.9), font: fonts.text.clone() },
-                        ));
-                    });
-            }
+                        });
+                        if !is_locked {
+                            level_ui.insert((
+                                Button,
+                                Interaction::default(),
+                                ButtonLevelData { level_idx: idx },
+                                UiSound::default(),
+                            ));
+                        }
+
+                        level_ui.with_children(|parent| {
+                            let color = if is_locked { LOCKED_TEXT } else { TEXT_COLOR };
+                            parent.spawn(TextBundle::from_section(
+                                level.name.clone(),
+                                TextStyle { font_size: 40.0, color, font: fonts.text.clone() },
+                            ));
+                            if let Some(record) = progress.levels.get(&curr_area.to_string(), &level.name) {
+                                parent.spawn(TextBundle::from_section(
+                                    format!("{}/{} stars", record.best_stars, MAX_STARS),
+                                    TextStyle { color: STAR_COLOR, ..requirement_style.clone() },
+                                ));
+                            }
+                            for req in unmet.iter() {
+                                parent.spawn(TextBundle::from_section(
+                                    ctx.describe(req, curr_area, Some(idx)),
+                                    requirement_style.clone(),
+                                ));
+                            }
+                        });
+                    }
+                });
         });
 }
 
+/// Switches the area viewer over to another area
+#[derive(Component)]
+struct AreaTab(Area);
+
+fn spawn_area_tab(
+    parent: &mut ChildBuilder,
+    fonts: &UiAssets,
+    area: &Area,
+    is_current: bool,
+    requirements: &[String],
+    requirement_style: &TextStyle,
+) {
+    let is_locked = !requirements.is_empty();
+    let (background, text_color) = match (is_locked, is_current) {
+        (true, _) => (LOCKED_BUTTON, LOCKED_TEXT),
+        (false, true) => (PRESSED_BUTTON, TEXT_COLOR),
+        (false, false) => (NORMAL_BUTTON, TEXT_COLOR),
+    };
+    let mut tab = parent.spawn(NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::axes(Val::Px(15.0), Val::Px(5.0)),
+            border: UiRect::all(Val::Px(3.0)),
+            ..default()
+        },
+        border_color: BorderColor(Color::BLACK),
+        background_color: background.into(),
+        ..default()
+    });
+    if !is_locked && !is_current {
+        tab.insert((Button, Interaction::default(), AreaTab(area.clone())));
+    }
+
+    tab.with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            area.to_string(),
+            TextStyle { font_size: 28.0, color: text_color, font: fonts.text.clone() },
+        ));
+        for req in requirements {
+            parent.spawn(TextBundle::from_section(req.clone(), requirement_style.clone()));
+        }
+    });
+}
+
+fn area_tab_buttons(mut q_interaction: ButtonInteractions<AreaTab>, mut next_state: ResMut<NextState<AppState>>) {
+    for (interaction, mut color, tab) in &mut q_interaction {
+        match *interaction {
+            Interaction::Pressed => {
+                *color = PRESSED_BUTTON.into();
+                next_state.set(AppState::AreaViewer { curr_area: tab.0.clone() });
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
 /// Removes the ui that is only alive for the lifetime of the scene
 pub(crate) fn cleanup(mut commands: Commands, ui_q: Query<Entity, With<StateUIMaster>>) {
     for e in ui_q.iter() {
@@ -0,0 +1,169 @@
+use bevy::{prelude::*, ui::FocusPolicy};
+
+use super::{ButtonInteractions, HOVERED_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON, TEXT_COLOR};
+use crate::{
+    assets::UiAssets,
+    audio_events::UiSound,
+    data_read::LevelDb,
+    expedition::{ActiveLevel, ExpeditionPhase, LevelChange, PauseState, PhaseChange},
+    settings::SettingsMenu,
+};
+
+const OVERLAY_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.6);
+const PANEL_COLOR: Color = Color::rgb(0.1, 0.1, 0.12);
+
+pub struct PauseUIPlugin;
+
+impl Plugin for PauseUIPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(PauseState::Paused), setup_pause_menu)
+            .add_systems(OnExit(PauseState::Paused), cleanup_pause_menu)
+            .add_systems(Update, pause_buttons.run_if(in_state(PauseState::Paused)));
+    }
+}
+
+#[derive(Component)]
+struct PauseUIMaster;
+
+#[derive(Component, Clone, Copy)]
+enum PauseButton {
+    Resume,
+    Restart,
+    Settings,
+    Abandon,
+}
+
+impl PauseButton {
+    const ALL: [PauseButton; 4] =
+        [PauseButton::Resume, PauseButton::Restart, PauseButton::Settings, PauseButton::Abandon];
+
+    fn label(&self) -> &'static str {
+        match self {
+            PauseButton::Resume => "Resume",
+            PauseButton::Restart => "Restart level",
+            PauseButton::Settings => "Settings",
+            PauseButton::Abandon => "Abandon",
+        }
+    }
+}
+
+fn pause_buttons(
+    mut q_interaction: ButtonInteractions<PauseButton>,
+    active_level: Res<ActiveLevel>,
+    mut ev_phase: EventWriter<PhaseChange>,
+    mut next_pause: ResMut<NextState<PauseState>>,
+    mut next_settings: ResMut<NextState<SettingsMenu>>,
+    mut ev_level_change: EventWriter<LevelChange>,
+) {
+    for (interaction, mut color, button) in &mut q_interaction {
+        match *interaction {
+            Interaction::Pressed => {
+                *color = PRESSED_BUTTON.into();
+                match button {
+                    PauseButton::Resume => next_pause.set(PauseState::Running),
+                    PauseButton::Settings => next_settings.set(SettingsMenu::Open),
+                    PauseButton::Restart => {
+                        // same seed so the restarted level has the same treasures in the same spots
+                        ev_level_change.send(LevelChange {
+                            seed: Some(active_level.seed),
+                            ..LevelChange::new(active_level.area.clone(), active_level.level_idx)
+                        });
+                    }
+                    PauseButton::Abandon => ev_phase.send(PhaseChange(ExpeditionPhase::Leaving)),
+                }
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+fn setup_pause_menu(
+    mut commands: Commands,
+    fonts: Res<UiAssets>,
+    active_level: Res<ActiveLevel>,
+    level_db: Res<LevelDb>,
+) {
+    debug!("setting up ui for the pause menu");
+    let title_style = TextStyle { font_size: 40.0, color: TEXT_COLOR, font: fonts.text.clone() };
+    let text_style = TextStyle { font_size: 20.0, color: TEXT_COLOR, font: fonts.text.clone() };
+    let level_name = level_db
+        .get(&active_level.area.to_string())
+        .and_then(|area| area.levels.get(active_level.level_idx))
+        .map_or_else(String::new, |level| format!("{} - {}", active_level.area, level.name));
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: OVERLAY_COLOR.into(),
+                // keeps clicks from reaching the toolbar and leave button under the menu
+                focus_policy: FocusPolicy::Block,
+                // under the settings so they can be opened from here
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+            PauseUIMaster,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        row_gap: Val::Px(10.0),
+                        padding: UiRect::all(Val::Px(20.0)),
+                        min_width: Val::Px(300.0),
+                        ..default()
+                    },
+                    background_color: PANEL_COLOR.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Paused", title_style.clone()));
+                    parent.spawn(TextBundle::from_section(level_name, text_style.clone()));
+                    for button in PauseButton::ALL {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        padding: UiRect::axes(Val::Px(15.0), Val::Px(5.0)),
+                                        border: UiRect::all(Val::Px(3.0)),
+                                        width: Val::Percent(100.0),
+                                        justify_content: JustifyContent::Center,
+                                        ..default()
+                                    },
+                                    border_color: BorderColor(Color::BLACK),
+                                    background_color: NORMAL_BUTTON.into(),
+                                    ..default()
+                                },
+                                button,
+                                UiSound::default(),
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    button.label(),
+                                    TextStyle { font_size: 28.0, ..text_style.clone() },
+                                ));
+                            });
+                    }
+                });
+        });
+}
+
+fn cleanup_pause_menu(mut commands: Commands, q_root: Query<Entity, With<PauseUIMaster>>) {
+    for e in q_root.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
@@ -0,0 +1,241 @@
+use bevy::{prelude::*, ui::FocusPolicy};
+
+use super::{ButtonInteractions, HOVERED_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON, TEXT_COLOR};
+use crate::{
+    assets::UiAssets,
+    settings::{key_name, KeyAction, Rebinding, Settings, SettingsChange, SettingsMenu, VolumeKind},
+};
+
+const OVERLAY_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.75);
+const PANEL_COLOR: Color = Color::rgb(0.1, 0.1, 0.12);
+const WAITING_COLOR: Color = Color::rgb(1.0, 0.84, 0.3);
+
+pub struct SettingsUIPlugin;
+
+impl Plugin for SettingsUIPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(SettingsMenu::Open), setup_settings)
+            .add_systems(OnExit(SettingsMenu::Open), (cleanup_settings, stop_rebinding))
+            .add_systems(Update, settings_buttons.run_if(in_state(SettingsMenu::Open)))
+            // rebuilt on every change so the values shown stay up to date
+            .add_systems(
+                Update,
+                (cleanup_settings, setup_settings)
+                    .chain()
+                    .after(settings_buttons)
+                    .run_if(in_state(SettingsMenu::Open))
+                    .run_if(resource_changed::<Settings>().or_else(resource_changed::<Rebinding>())),
+            );
+    }
+}
+
+#[derive(Component)]
+struct SettingsUIMaster;
+
+#[derive(Component, Clone, Copy)]
+enum SettingsButton {
+    Change(SettingsChange),
+    Close,
+}
+
+fn settings_buttons(
+    mut q_interaction: ButtonInteractions<SettingsButton>,
+    mut ev_change: EventWriter<SettingsChange>,
+    mut next_menu: ResMut<NextState<SettingsMenu>>,
+) {
+    for (interaction, mut color, button) in &mut q_interaction {
+        match *interaction {
+            Interaction::Pressed => {
+                *color = PRESSED_BUTTON.into();
+                match button {
+                    SettingsButton::Change(change) => ev_change.send(*change),
+                    SettingsButton::Close => next_menu.set(SettingsMenu::Closed),
+                }
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+fn setup_settings(mut commands: Commands, fonts: Res<UiAssets>, settings: Res<Settings>, rebinding: Res<Rebinding>) {
+    debug!("setting up ui for the settings");
+    let title_style = TextStyle { font_size: 40.0, color: TEXT_COLOR, font: fonts.text.clone() };
+    let text_style = TextStyle { font_size: 20.0, color: TEXT_COLOR, font: fonts.text.clone() };
+    let on_off = |on: bool| if on { "On" } else { "Off" };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: OVERLAY_COLOR.into(),
+                // keeps clicks from reaching whatever is under the settings
+                focus_policy: FocusPolicy::Block,
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            SettingsUIMaster,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(6.0),
+                        padding: UiRect::all(Val::Px(20.0)),
+                        min_width: Val::Px(460.0),
+                        ..default()
+                    },
+                    background_color: PANEL_COLOR.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Settings", title_style.clone()));
+
+                    spawn_heading(parent, "Audio", &title_style);
+                    for kind in VolumeKind::ALL {
+                        let value = format!("{}%", (settings.volume(kind) * 100.0).round());
+                        spawn_settings_row(parent, &text_style, kind.name(), |parent| {
+                            spawn_button(parent, &text_style, "-", SettingsChange::Volume { kind, up: false });
+                            parent.spawn(TextBundle::from_section(value, text_style.clone()));
+                            spawn_button(parent, &text_style, "+", SettingsChange::Volume { kind, up: true });
+                        });
+                    }
+
+                    spawn_heading(parent, "Video", &title_style);
+                    let rows = [
+                        ("Window mode", settings.window_mode.name().to_string(), SettingsChange::CycleWindowMode),
+                        (
+                            "Resolution",
+                            format!("{}x{}", settings.resolution.0, settings.resolution.1),
+                            SettingsChange::CycleResolution,
+                        ),
+                        ("VSync", on_off(settings.vsync).to_string(), SettingsChange::ToggleVsync),
+                        (
+                            "Reduced motion",
+                            on_off(settings.reduced_motion).to_string(),
+                            SettingsChange::ToggleReducedMotion,
+                        ),
+                    ];
+                    for (label, value, change) in rows {
+                        spawn_settings_row(parent, &text_style, label, |parent| {
+                            spawn_button(parent, &text_style, &value, change);
+                        });
+                    }
+
+                    spawn_heading(parent, "Controls", &title_style);
+                    for action in KeyAction::ALL {
+                        let waiting = rebinding.0 == Some(action);
+                        let (key, style) = match waiting {
+                            true => {
+                                ("Press a key".to_string(), TextStyle { color: WAITING_COLOR, ..text_style.clone() })
+                            }
+                            false => (key_name(settings.keybinds.key(action)).to_string(), text_style.clone()),
+                        };
+                        spawn_settings_row(parent, &text_style, action.name(), |parent| {
+                            spawn_button(parent, &style, &key, SettingsChange::Rebind(action));
+                        });
+                    }
+
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    margin: UiRect::top(Val::Px(14.0)),
+                                    padding: UiRect::axes(Val::Px(15.0), Val::Px(5.0)),
+                                    border: UiRect::all(Val::Px(3.0)),
+                                    align_self: AlignSelf::Center,
+                                    ..default()
+                                },
+                                border_color: BorderColor(Color::BLACK),
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            SettingsButton::Close,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(
+                                "Back",
+                                TextStyle { font_size: 28.0, ..text_style.clone() },
+                            ));
+                        });
+                });
+        });
+}
+
+fn spawn_heading(parent: &mut ChildBuilder, title: &str, title_style: &TextStyle) {
+    parent.spawn(
+        TextBundle::from_section(title, TextStyle { font_size: 28.0, ..title_style.clone() })
+            .with_style(Style { margin: UiRect::top(Val::Px(10.0)), ..default() }),
+    );
+}
+
+/// A label on the left with the controls for changing it on the right
+fn spawn_settings_row(
+    parent: &mut ChildBuilder,
+    text_style: &TextStyle,
+    label: &str,
+    spawn_controls: impl FnOnce(&mut ChildBuilder),
+) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                justify_content: JustifyContent::SpaceBetween,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(label, text_style.clone()));
+            parent
+                .spawn(NodeBundle {
+                    style: Style { column_gap: Val::Px(10.0), align_items: AlignItems::Center, ..default() },
+                    ..default()
+                })
+                .with_children(spawn_controls);
+        });
+}
+
+fn spawn_button(parent: &mut ChildBuilder, text_style: &TextStyle, text: &str, change: SettingsChange) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    padding: UiRect::axes(Val::Px(10.0), Val::Px(3.0)),
+                    border: UiRect::all(Val::Px(2.0)),
+                    min_width: Val::Px(30.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                border_color: BorderColor(Color::BLACK),
+                background_color: NORMAL_BUTTON.into(),
+                ..default()
+            },
+            SettingsButton::Change(change),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(text, text_style.clone()));
+        });
+}
+
+fn cleanup_settings(mut commands: Commands, q_root: Query<Entity, With<SettingsUIMaster>>) {
+    for e in q_root.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
+
+fn stop_rebinding(mut rebinding: ResMut<Rebinding>) {
+    rebinding.0 = None;
+}
@@ -0,0 +1,246 @@
+use bevy::prelude::*;
+
+use super::{
+    cleanup,
+    collection::{rarity_color, rarity_label},
+    spawn_menu_buttons, ButtonInteractions, MenuButton, StateUIMaster, HOVERED_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON,
+    TEXT_COLOR,
+};
+use crate::{
+    assets::UiAssets,
+    audio_events::UiSound,
+    data_read::{ShopDb, TreasureDb, UnlockPrice},
+    economy::ShopAction,
+    expedition::Area,
+    save::Progress,
+    tools::{ToolType, UpgradeKind},
+    AppState,
+};
+
+const COIN_COLOR: Color = Color::rgb(1.0, 0.84, 0.3);
+const TOO_EXPENSIVE_COLOR: Color = Color::rgb(0.75, 0.3, 0.3);
+const COLUMN_BACKGROUND: Color = Color::rgb(0.1, 0.1, 0.12);
+
+pub struct ShopUIPlugin;
+
+impl Plugin for ShopUIPlugin {
+    fn build(&self, app: &mut App) {
+        for curr_area in Area::ALL {
+            app.add_systems(OnEnter(AppState::Shop { curr_area: curr_area.clone() }), setup_shop)
+                .add_systems(OnExit(AppState::Shop { curr_area }), cleanup);
+        }
+        // rebuilt whenever something is bought or sold so the prices and coins stay up to date
+        app.add_systems(Update, shop_buttons.run_if(in_shop_state)).add_systems(
+            Update,
+            (cleanup, setup_shop)
+                .chain()
+                .after(shop_buttons)
+                .run_if(in_shop_state)
+                .run_if(resource_changed::<Progress>().or_else(resource_changed::<TreasureDb>())),
+        );
+    }
+}
+
+fn in_shop_state(app_state: Res<State<AppState>>) -> bool {
+    matches!(app_state.get(), AppState::Shop { .. })
+}
+
+#[derive(Component)]
+struct ShopButton(ShopAction);
+
+fn shop_buttons(mut q_interaction: ButtonInteractions<ShopButton>, mut ev_shop: EventWriter<ShopAction>) {
+    for (interaction, mut color, button) in &mut q_interaction {
+        match *interaction {
+            Interaction::Pressed => {
+                *color = PRESSED_BUTTON.into();
+                ev_shop.send(button.0);
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+fn setup_shop(
+    mut commands: Commands,
+    fonts: Res<UiAssets>,
+    tdb: Res<TreasureDb>,
+    shop: Res<ShopDb>,
+    progress: Res<Progress>,
+) {
+    debug!("setting up ui for the shop");
+    let title_style = TextStyle { font_size: 40.0, color: TEXT_COLOR, font: fonts.text.clone() };
+    let text_style = TextStyle { font_size: 20.0, color: TEXT_COLOR, font: fonts.text.clone() };
+    let coins = progress.wallet.coins;
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    padding: UiRect::all(Val::Px(20.0)),
+                    ..default()
+                },
+                ..default()
+            },
+            StateUIMaster,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("Shop", title_style.clone()));
+            parent.spawn(TextBundle::from_section(
+                format!("{} coins", coins),
+                TextStyle { color: COIN_COLOR, ..title_style.clone() },
+            ));
+            spawn_menu_buttons(parent, &fonts, &[MenuButton::Back]);
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(20.0),
+                        margin: UiRect::top(Val::Px(20.0)),
+                        align_items: AlignItems::FlexStart,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    spawn_column(parent, "Sell", &title_style, |parent| {
+                        let mut owned = progress.trove.owned().peekable();
+                        if owned.peek().is_none() {
+                            parent.spawn(TextBundle::from_section("Nothing to sell", text_style.clone()));
+                        }
+                        for held in owned {
+                            let Some(info) = tdb.get_by_id(held.id) else {
+                                continue;
+                            };
+                            let label = TextStyle { color: rarity_color(info.rarity), ..text_style.clone() };
+                            spawn_shop_row(
+                                parent,
+                                &fonts,
+                                format!("{} x{} ({})", info.name, held.owned, rarity_label(info.rarity)),
+                                label,
+                                Ok((format!("+{}", info.value), COIN_COLOR, ShopAction::Sell { treasure: held.id })),
+                            );
+                        }
+                    });
+
+                    spawn_column(parent, "Upgrades", &title_style, |parent| {
+                        for kind in UpgradeKind::ALL {
+                            let max_level = shop.upgrade_costs(kind).len();
+                            if max_level == 0 {
+                                continue;
+                            }
+                            let level = progress.upgrades.level(kind);
+                            let label = format!("{} {}/{}", kind.name(), level, max_level);
+                            let buy = progress
+                                .upgrades
+                                .next_cost(kind, &shop)
+                                .map(|cost| (format!("{}", cost), price_color(cost, coins), ShopAction::Upgrade(kind)))
+                                .ok_or("Maxed");
+                            spawn_shop_row(parent, &fonts, label, text_style.clone(), buy);
+                        }
+                    });
+
+                    spawn_column(parent, "Tools", &title_style, |parent| {
+                        for &UnlockPrice { rotation, cost } in shop.unlocks.iter() {
+                            let tool = ToolType::Pickaxe { rotation };
+                            let label = tool.name();
+                            let buy = (!progress.tools.is_unlocked(&tool))
+                                .then(|| (format!("{}", cost), price_color(cost, coins), ShopAction::Unlock(rotation)))
+                                .ok_or("Owned");
+                            spawn_shop_row(parent, &fonts, label, text_style.clone(), buy);
+                        }
+                    });
+                });
+        });
+}
+
+fn price_color(cost: u32, coins: u32) -> Color {
+    if cost > coins {
+        TOO_EXPENSIVE_COLOR
+    } else {
+        COIN_COLOR
+    }
+}
+
+fn spawn_column(
+    parent: &mut ChildBuilder,
+    title: &str,
+    title_style: &TextStyle,
+    spawn_rows: impl FnOnce(&mut ChildBuilder),
+) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(8.0),
+                min_width: Val::Px(280.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            background_color: COLUMN_BACKGROUND.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(title, TextStyle { font_size: 30.0, ..title_style.clone() }));
+            spawn_rows(parent);
+        });
+}
+
+/// A line in the shop with a button on the end, the button is replaced by the `Err` text when there is nothing to buy
+fn spawn_shop_row(
+    parent: &mut ChildBuilder,
+    fonts: &UiAssets,
+    label: String,
+    label_style: TextStyle,
+    button: Result<(String, Color, ShopAction), &str>,
+) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                justify_content: JustifyContent::SpaceBetween,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(12.0),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(label, label_style.clone()));
+            let (price, price_color, action) = match button {
+                Ok(button) => button,
+                Err(done) => {
+                    parent.spawn(TextBundle::from_section(done, label_style));
+                    return;
+                }
+            };
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            padding: UiRect::axes(Val::Px(10.0), Val::Px(3.0)),
+                            border: UiRect::all(Val::Px(2.0)),
+                            ..default()
+                        },
+                        border_color: BorderColor(Color::BLACK),
+                        background_color: NORMAL_BUTTON.into(),
+                        ..default()
+                    },
+                    ShopButton(action),
+                    UiSound::default(),
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        price,
+                        TextStyle { font_size: 20.0, color: price_color, font: fonts.text.clone() },
+                    ));
+                });
+        });
+}
@@ -0,0 +1,141 @@
+use bevy::prelude::*;
+use bevy_mod_picking::backend::prelude::Pickable;
+
+use crate::{
+    assets::UiAssets,
+    expedition::ExpeditionPersist,
+    stability::{Stability, StabilityLevel},
+    AppState, SystemOrder,
+};
+
+const METER_WIDTH: f32 = 260.0;
+const STABLE_COLOR: Color = Color::rgb(0.35, 0.75, 0.35);
+const SHAKY_COLOR: Color = Color::rgb(0.9, 0.65, 0.2);
+const CRITICAL_COLOR: Color = Color::rgb(0.85, 0.2, 0.2);
+const CRITICAL_PULSE_COLOR: Color = Color::rgb(0.4, 0.05, 0.05);
+/// How many times a second the meter pulses when critical
+const PULSE_SPEED: f32 = 2.0;
+
+pub struct StabilityMeterPlugin;
+
+impl Plugin for StabilityMeterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::Expedition), init_stability_meter).add_systems(
+            Update,
+            (
+                update_stability_meter.run_if(resource_changed::<Stability>()),
+                pulse_critical_meter.after(update_stability_meter),
+            )
+                .run_if(in_state(AppState::Expedition))
+                .in_set(SystemOrder::Render)
+                .after(SystemOrder::Logic),
+        );
+    }
+}
+
+#[derive(Component)]
+struct StabilityMeterFill;
+
+#[derive(Component)]
+struct StabilityMeterText;
+
+fn init_stability_meter(mut commands: Commands, ui_assets: Res<UiAssets>, stability: Res<Stability>) {
+    let text_style = TextStyle { font: ui_assets.text.clone(), font_size: 22.0, color: Color::rgb_u8(255, 241, 169) };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(10.0),
+                    top: Val::Px(10.0),
+                    width: Val::Px(METER_WIDTH),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.0),
+                    ..default()
+                },
+                z_index: ZIndex::Global(3),
+                ..default()
+            },
+            Pickable::IGNORE,
+            ExpeditionPersist,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_sections([
+                    TextSection::new("Stability ", text_style.clone()),
+                    TextSection::new(meter_text(&stability), TextStyle { color: Color::WHITE, ..text_style }),
+                ]),
+                StabilityMeterText,
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Px(14.0),
+                        border: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.1, 0.1, 0.1).into(),
+                    border_color: Color::BLACK.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(stability.fraction() * 100.0),
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                            background_color: level_color(stability.level()).into(),
+                            ..default()
+                        },
+                        StabilityMeterFill,
+                    ));
+                });
+        });
+}
+
+fn meter_text(stability: &Stability) -> String {
+    format!("{} / {}", stability.remaining.max(0), stability.start)
+}
+
+fn level_color(level: StabilityLevel) -> Color {
+    match level {
+        StabilityLevel::Stable => STABLE_COLOR,
+        StabilityLevel::Shaky => SHAKY_COLOR,
+        StabilityLevel::Critical => CRITICAL_COLOR,
+    }
+}
+
+fn update_stability_meter(
+    mut q_fill: Query<(&mut Style, &mut BackgroundColor), With<StabilityMeterFill>>,
+    mut q_text: Query<&mut Text, With<StabilityMeterText>>,
+    stability: Res<Stability>,
+) {
+    if let Ok((mut style, mut color)) = q_fill.get_single_mut() {
+        style.width = Val::Percent(stability.fraction() * 100.0);
+        *color = level_color(stability.level()).into();
+    }
+    if let Ok(mut text) = q_text.get_single_mut() {
+        text.sections[1].value = meter_text(&stability);
+    }
+}
+
+/// Fades the meter in and out while the cave is about to come down
+fn pulse_critical_meter(
+    mut q_fill: Query<&mut BackgroundColor, With<StabilityMeterFill>>,
+    stability: Res<Stability>,
+    time: Res<Time>,
+) {
+    if stability.level() != StabilityLevel::Critical {
+        return;
+    }
+    let Ok(mut color) = q_fill.get_single_mut() else {
+        return;
+    };
+    let t = ((time.elapsed_seconds() * PULSE_SPEED * std::f32::consts::TAU).sin() + 1.0) / 2.0;
+    let [r, g, b, _] = CRITICAL_COLOR.as_rgba_f32();
+    let [pr, pg, pb, _] = CRITICAL_PULSE_COLOR.as_rgba_f32();
+    *color = Color::rgb(pr + (r - pr) * t, pg + (g - pg) * t, pb + (b - pb) * t).into();
+}
@@ -0,0 +1,61 @@
+//! Data fixtures shared by the integration tests, not every test uses all of them
+#![allow(dead_code)]
+
+use miner_thing::data_read::{parse_data, LevelDb, TreasureDb};
+
+/// Level 0 of The Caves has no requirements and each level after it has one of every kind of requirement.
+/// The Collapse needs the last level of The Caves cleared, its levels cover every stability profile and a level
+/// with its own stability thresholds
+pub fn levels() -> LevelDb {
+    parse_data(
+        "{
+            'The Caves': {
+                levels: [
+                    { name: 'First', size: [5, 5], stability: 'Normal' },
+                    { name: 'Previous', size: [5, 5], stability: 'Normal', requires: ['ClearPrevious'] },
+                    {
+                        name: 'Named',
+                        size: [5, 5],
+                        stability: 'Normal',
+                        requires: [{ ClearLevel: { area: 'The Caves', level: 'First' } }],
+                    },
+                    { name: 'Treasure', size: [5, 5], stability: 'Normal', requires: [{ Treasure: 4 }] },
+                    { name: 'Stars', size: [5, 5], stability: 'Normal', requires: [{ Stars: 5 }] },
+                    {
+                        name: 'Tool',
+                        size: [5, 5],
+                        stability: 'Normal',
+                        requires: [{ Tool: { Pickaxe: { rotation: 'Cross' } } }],
+                    },
+                ],
+            },
+            'The Collapse': {
+                requires: ['ClearPrevious'],
+                levels: [
+                    { name: 'Deep', size: [5, 5], stability: 'Normal' },
+                    { name: 'Fragile', size: [5, 5], stability: 'Fragile' },
+                    {
+                        name: 'Steady',
+                        size: [5, 5],
+                        stability: 'Fragile',
+                        thresholds: { shaky: 0.2, critical: 0.1 },
+                    },
+                ],
+            },
+        }",
+    )
+    .unwrap()
+}
+
+/// Two common treasures worth 30 and 70, a fossil that levels ask for and a geode worth 40
+pub fn treasures() -> TreasureDb {
+    parse_data(
+        "[
+            { id: 1, name: 'Coin', shape: [0], width: 1, height: 1, value: 30 },
+            { id: 2, name: 'Ring', shape: [1], width: 1, height: 1, value: 70 },
+            { id: 4, name: 'Fossil', shape: [0], width: 1, height: 1 },
+            { id: 7, name: 'Geode', shape: [0], width: 1, height: 1, value: 40 },
+        ]",
+    )
+    .unwrap()
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use miner_thing::{
+    data_read::{parse_data, ShopDb},
+    economy::{ShopAction, ShopError},
+    save::Progress,
+    tools::{PickaxeRotation, ToolType, UpgradeKind},
+};
+
+use common::treasures;
+
+fn shop() -> ShopDb {
+    parse_data(
+        "{
+            upgrades: [{ kind: 'Damage', costs: [100, 250] }],
+            unlocks: [{ rotation: 'Vertical', cost: 150 }],
+        }",
+    )
+    .unwrap()
+}
+
+fn progress_with_coins(coins: u32) -> Progress {
+    let mut progress = Progress::default();
+    progress.wallet.coins = coins;
+    progress
+}
+
+#[test]
+fn upgrade_without_enough_coins_changes_nothing() {
+    let mut progress = progress_with_coins(99);
+    let result = ShopAction::Upgrade(UpgradeKind::Damage).apply(&mut progress, &treasures(), &shop());
+    assert_eq!(result, Err(ShopError::TooExpensive { cost: 100 }));
+    assert_eq!(progress.wallet.coins, 99);
+    assert_eq!(progress.upgrades.level(UpgradeKind::Damage), 0);
+}
+
+#[test]
+fn unlock_without_enough_coins_changes_nothing() {
+    let mut progress = progress_with_coins(149);
+    let result = ShopAction::Unlock(PickaxeRotation::Vertical).apply(&mut progress, &treasures(), &shop());
+    assert_eq!(result, Err(ShopError::TooExpensive { cost: 150 }));
+    assert_eq!(progress.wallet.coins, 149);
+    assert!(!progress.tools.is_unlocked(&ToolType::Pickaxe { rotation: PickaxeRotation::Vertical }));
+}
+
+#[test]
+fn buying_an_upgrade_twice_charges_each_level_until_maxed() {
+    let (tdb, shop) = (treasures(), shop());
+    let mut progress = progress_with_coins(400);
+    let upgrade = ShopAction::Upgrade(UpgradeKind::Damage);
+    assert_eq!(upgrade.apply(&mut progress, &tdb, &shop), Ok(()));
+    assert_eq!(upgrade.apply(&mut progress, &tdb, &shop), Ok(()));
+    assert_eq!(progress.wallet.coins, 50);
+    assert_eq!(progress.upgrades.level(UpgradeKind::Damage), 2);
+    assert_eq!(upgrade.apply(&mut progress, &tdb, &shop), Err(ShopError::MaxedOut));
+    assert_eq!(progress.wallet.coins, 50);
+}
+
+#[test]
+fn unlocking_twice_only_charges_once() {
+    let (tdb, shop) = (treasures(), shop());
+    let mut progress = progress_with_coins(400);
+    let unlock = ShopAction::Unlock(PickaxeRotation::Vertical);
+    assert_eq!(unlock.apply(&mut progress, &tdb, &shop), Ok(()));
+    assert_eq!(unlock.apply(&mut progress, &tdb, &shop), Err(ShopError::AlreadyUnlocked));
+    assert_eq!(progress.wallet.coins, 250);
+}
+
+#[test]
+fn unpriced_unlock_cannot_be_bought() {
+    let mut progress = progress_with_coins(1000);
+    let result = ShopAction::Unlock(PickaxeRotation::Cross).apply(&mut progress, &treasures(), &shop());
+    assert_eq!(result, Err(ShopError::NotForSale));
+    assert_eq!(progress.wallet.coins, 1000);
+}
+
+#[test]
+fn selling_a_treasure_in_the_trove_pays_its_value_and_keeps_it_in_the_collection() {
+    let (tdb, shop) = (treasures(), shop());
+    let mut progress = progress_with_coins(0);
+    progress.trove.collect(7, "Level 1");
+    progress.trove.collect(7, "Level 2");
+
+    let sell = ShopAction::Sell { treasure: 7 };
+    assert_eq!(sell.apply(&mut progress, &tdb, &shop), Ok(()));
+    assert_eq!(progress.wallet.coins, 40);
+    let held = progress.trove.get(7).unwrap();
+    assert_eq!((held.owned, held.times_collected), (1, 2));
+
+    assert_eq!(sell.apply(&mut progress, &tdb, &shop), Ok(()));
+    assert_eq!(sell.apply(&mut progress, &tdb, &shop), Err(ShopError::NoneOwned));
+    assert_eq!(progress.wallet.coins, 80);
+    assert_eq!(progress.trove.get(7).unwrap().times_collected, 2);
+}
+
+#[test]
+fn selling_a_treasure_that_does_not_exist_fails() {
+    let mut progress = progress_with_coins(0);
+    let result = ShopAction::Sell { treasure: 3 }.apply(&mut progress, &treasures(), &shop());
+    assert_eq!(result, Err(ShopError::UnknownTreasure));
+}
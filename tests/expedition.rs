@@ -0,0 +1,83 @@
+mod common;
+
+use miner_thing::{
+    data_read::{LevelDb, TreasureDb},
+    expedition::{ActiveLevel, Area, ExpeditionPhase, LevelChange, LevelChangeError},
+    progression::RequirementCtx,
+    save::Progress,
+    AppState,
+};
+
+use common::levels;
+
+fn find_in(level_db: &LevelDb, change: &LevelChange, last_played: &ActiveLevel) -> Result<String, LevelChangeError> {
+    let (treasure_db, progress) = (TreasureDb::default(), Progress::default());
+    let ctx = RequirementCtx { progress: &progress, level_db, treasure_db: &treasure_db };
+    change.find_level(&ctx, last_played).map(|level| level.name.clone())
+}
+
+fn find(change: &LevelChange, last_played: &ActiveLevel) -> Result<String, LevelChangeError> {
+    find_in(&levels(), change, last_played)
+}
+
+#[test]
+fn existing_unlocked_level_is_found() {
+    assert_eq!(find(&LevelChange::new(Area::TheCaves, 0), &ActiveLevel::default()), Ok("First".to_string()));
+}
+
+#[test]
+fn missing_level_is_an_error() {
+    let last_played = ActiveLevel::default();
+    assert_eq!(find(&LevelChange::new(Area::TheCaves, 9), &last_played), Err(LevelChangeError::MissingLevel));
+    let no_areas = LevelDb::default();
+    let change = LevelChange::new(Area::TheCollapse, 0);
+    assert_eq!(find_in(&no_areas, &change, &last_played), Err(LevelChangeError::MissingArea));
+}
+
+#[test]
+fn locked_level_is_refused_unless_ignored_or_replayed() {
+    let change = LevelChange::new(Area::TheCaves, 1);
+    assert_eq!(find(&change, &ActiveLevel::default()), Err(LevelChangeError::Locked));
+
+    let forced = LevelChange { ignore_requirements: true, ..LevelChange::new(Area::TheCaves, 1) };
+    assert_eq!(find(&forced, &ActiveLevel::default()), Ok("Previous".to_string()));
+
+    let replay = ActiveLevel { area: Area::TheCaves, level_idx: 1, seed: 3 };
+    assert_eq!(find(&change, &replay), Ok("Previous".to_string()));
+}
+
+#[test]
+fn failed_level_change_falls_back_to_the_area_viewer_only_between_levels() {
+    let change = LevelChange::new(Area::TheCollapse, 9);
+    assert_eq!(
+        change.fallback_state(&AppState::LevelTransition),
+        Some(AppState::AreaViewer { curr_area: Area::TheCollapse })
+    );
+    assert_eq!(change.fallback_state(&AppState::AreaViewer { curr_area: Area::TheCaves }), None);
+}
+
+#[test]
+fn phase_moves_match_the_lifecycle() {
+    use ExpeditionPhase::*;
+    let all = [Inactive, Intro, Mining, Collapsing, Cleared, Failed, LeftEarly, Leaving];
+    let allowed = [
+        (Inactive, Intro),
+        (Intro, Mining),
+        (Mining, Collapsing),
+        (Mining, Cleared),
+        (Mining, LeftEarly),
+        (Mining, Leaving),
+        (Collapsing, Failed),
+        (Collapsing, Leaving),
+        (Cleared, Leaving),
+        (Failed, Leaving),
+        (LeftEarly, Leaving),
+    ];
+    for from in all {
+        for to in all {
+            // every phase can always be reset back to inactive
+            let expected = to == Inactive || allowed.contains(&(from, to));
+            assert_eq!(from.can_move_to(to), expected, "{from:?} -> {to:?}");
+        }
+    }
+}
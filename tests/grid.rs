@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use bevy::math::Vec2;
+use miner_thing::point::{idx_to_xy, tile_to_world, world_to_tile, xy_to_idx, Grid, IPoint, Rotation, UPoint};
+use proptest::prelude::*;
+
+/// A grid with every cell holding its own index so moved cells can be tracked
+fn numbered_grid() -> impl Strategy<Value = Grid<usize>> {
+    (1..12usize, 1..12usize).prop_map(|(w, h)| Grid::from_vec(w, h, (0..w * h).collect()).unwrap())
+}
+
+fn rotation() -> impl Strategy<Value = Rotation> {
+    prop::sample::select(Rotation::ALL.to_vec())
+}
+
+proptest! {
+    #[test]
+    fn idx_and_xy_round_trip(width in 1..64usize, x in 0..64usize, y in 0..64usize) {
+        let x = x % width;
+        prop_assert_eq!(idx_to_xy(xy_to_idx(x, y, width), width), UPoint::new(x, y));
+    }
+
+    #[test]
+    fn xy_and_idx_round_trip(width in 1..64usize, idx in 0..4096usize) {
+        let pos = idx_to_xy(idx, width);
+        prop_assert!(pos.x < width);
+        prop_assert_eq!(xy_to_idx(pos.x, pos.y, width), idx);
+    }
+
+    #[test]
+    fn tile_and_world_round_trip(x in -500..500i32, y in -500..500i32) {
+        let pos = IPoint::new(x, y);
+        prop_assert_eq!(world_to_tile(tile_to_world(pos)), pos);
+    }
+
+    #[test]
+    fn get_matches_layout(grid in numbered_grid()) {
+        for (pos, cell) in grid.iter() {
+            prop_assert_eq!(*cell, pos.as_idx(grid.width()));
+            prop_assert_eq!(grid.get(pos), Some(cell));
+        }
+        prop_assert_eq!(grid.get(UPoint::new(grid.width(), 0)), None);
+        prop_assert_eq!(grid.get(UPoint::new(0, grid.height())), None);
+    }
+
+    #[test]
+    fn checked_only_allows_inside(grid in numbered_grid(), x in -20..20i32, y in -20..20i32) {
+        let inside = x >= 0 && y >= 0 && (x as usize) < grid.width() && (y as usize) < grid.height();
+        prop_assert_eq!(grid.checked(IPoint::new(x, y)).is_some(), inside);
+    }
+
+    #[test]
+    fn neighbours_are_adjacent_and_inside(grid in numbered_grid(), idx in 0..144usize) {
+        let pos = idx_to_xy(idx % (grid.width() * grid.height()), grid.width());
+        for n in grid.neighbours(pos) {
+            prop_assert!(grid.get(n).is_some());
+            prop_assert_eq!(pos.x.abs_diff(n.x) + pos.y.abs_diff(n.y), 1);
+        }
+    }
+
+    #[test]
+    fn rotation_round_trips(grid in numbered_grid(), rotation in rotation()) {
+        let rotated = grid.rotated(rotation);
+        prop_assert_eq!(rotated.rotated(rotation.inverse()), grid.clone());
+        if matches!(rotation, Rotation::Cw90 | Rotation::Cw270) {
+            prop_assert_eq!((rotated.width(), rotated.height()), (grid.height(), grid.width()));
+        }
+    }
+
+    #[test]
+    fn four_quarter_turns_is_identity(grid in numbered_grid()) {
+        let turned = (0..4).fold(grid.clone(), |g, _| g.rotated(Rotation::Cw90));
+        prop_assert_eq!(turned, grid);
+    }
+
+    #[test]
+    fn two_quarter_turns_is_half_turn(grid in numbered_grid()) {
+        prop_assert_eq!(grid.rotated(Rotation::Cw90).rotated(Rotation::Cw90), grid.rotated(Rotation::Cw180));
+    }
+
+    #[test]
+    fn flips_round_trip(grid in numbered_grid()) {
+        prop_assert_eq!(grid.flipped_x().flipped_x(), grid.clone());
+        prop_assert_eq!(grid.flipped_y().flipped_y(), grid.clone());
+        prop_assert_eq!(grid.flipped_x().flipped_y(), grid.rotated(Rotation::Cw180));
+    }
+}
+
+/// World position of every cell, relative to the middle of the grid
+fn centered_world_positions(grid: &Grid<usize>) -> HashMap<usize, Vec2> {
+    let middle = tile_to_world(IPoint::new(grid.width() as i32 - 1, grid.height() as i32 - 1)) / 2.0;
+    grid.iter().map(|(pos, cell)| (*cell, tile_to_world(IPoint::from(pos)) - middle)).collect()
+}
+
+#[test]
+fn quarter_turn_is_clockwise() {
+    // on screen:
+    // 3 4 5      0 3
+    // 0 1 2  ->  1 4
+    //            2 5
+    let grid = Grid::from_vec(3, 2, vec![0, 1, 2, 3, 4, 5]).unwrap();
+    let rotated = grid.rotated(Rotation::Cw90);
+    assert_eq!(rotated, Grid::from_vec(2, 3, vec![2, 5, 1, 4, 0, 3]).unwrap());
+
+    // every cell ends up where turning its world position by the sprite rotation puts it
+    let turn = Vec2::from_angle(Rotation::Cw90.radians());
+    let (before, after) = (centered_world_positions(&grid), centered_world_positions(&rotated));
+    for (cell, pos) in before {
+        assert!((turn.rotate(pos) - after[&cell]).length() < 0.001, "cell {cell} at {pos} moved to {}", after[&cell]);
+    }
+}
@@ -0,0 +1,46 @@
+use bevy::window::WindowMode;
+use clap::Parser;
+use miner_thing::{
+    cli::LaunchArgs,
+    expedition::{ActiveLevel, Area},
+    settings::{Settings, WindowModeSetting},
+    tools::{PickaxeRotation, ToolType},
+};
+
+fn args(flags: &[&str]) -> LaunchArgs {
+    LaunchArgs::try_parse_from(std::iter::once("miner_thing").chain(flags.iter().copied())).unwrap()
+}
+
+#[test]
+fn windowed_overrides_the_settings_file() {
+    let saved = Settings { window_mode: WindowModeSetting::Fullscreen, ..Default::default() };
+    let settings = saved.with_args(&args(&["--windowed"]));
+    assert_eq!(settings.window_mode, WindowModeSetting::Windowed);
+    assert_eq!(settings.window().mode, WindowMode::Windowed);
+}
+
+#[test]
+fn fullscreen_overrides_the_settings_file() {
+    let settings = Settings::default().with_args(&args(&["--fullscreen"]));
+    assert_eq!(settings.window().mode, WindowMode::BorderlessFullscreen);
+}
+
+#[test]
+fn no_window_flag_keeps_the_settings_file() {
+    let saved = Settings { window_mode: WindowModeSetting::Fullscreen, ..Default::default() };
+    assert_eq!(saved.with_args(&args(&[])).window_mode, WindowModeSetting::Fullscreen);
+}
+
+#[test]
+fn windowed_and_fullscreen_conflict() {
+    assert!(LaunchArgs::try_parse_from(["miner_thing", "--windowed", "--fullscreen"]).is_err());
+}
+
+#[test]
+fn tool_is_only_brought_into_the_launched_level() {
+    let launched = args(&["--area", "the-caves", "--level", "2", "--tool", "pickaxe-cross"]);
+    let level = |level_idx| ActiveLevel { area: Area::TheCaves, level_idx, seed: 0 };
+    assert_eq!(launched.launched_tool(&level(2)), Some(ToolType::Pickaxe { rotation: PickaxeRotation::Cross }));
+    assert_eq!(launched.launched_tool(&level(1)), None);
+    assert_eq!(args(&["--tool", "pickaxe-cross"]).launched_tool(&level(0)), None);
+}
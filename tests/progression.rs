@@ -0,0 +1,85 @@
+mod common;
+
+use miner_thing::{
+    expedition::Area,
+    progression::RequirementCtx,
+    save::Progress,
+    tools::{PickaxeRotation, ToolType},
+};
+
+use common::{levels, treasures};
+
+/// Whether the level of The Caves is unlocked before and after `meet` is applied to the progress
+fn unlocked_before_and_after(level_idx: usize, meet: impl FnOnce(&mut Progress)) -> (bool, bool) {
+    let (level_db, treasure_db) = (levels(), treasures());
+    let mut progress = Progress::default();
+    let before = RequirementCtx { progress: &progress, level_db: &level_db, treasure_db: &treasure_db }
+        .is_level_unlocked(&Area::TheCaves, level_idx);
+    meet(&mut progress);
+    let after = RequirementCtx { progress: &progress, level_db: &level_db, treasure_db: &treasure_db }
+        .is_level_unlocked(&Area::TheCaves, level_idx);
+    (before, after)
+}
+
+#[test]
+fn level_without_requirements_is_unlocked() {
+    assert_eq!(unlocked_before_and_after(0, |_| {}), (true, true));
+}
+
+#[test]
+fn clear_previous_needs_the_level_before_it_cleared() {
+    let result = unlocked_before_and_after(1, |progress| progress.levels.record_clear("The Caves", "First", 1));
+    assert_eq!(result, (false, true));
+}
+
+#[test]
+fn clear_previous_on_an_area_needs_the_last_level_of_the_area_before_it() {
+    let (level_db, treasure_db) = (levels(), treasures());
+    let mut progress = Progress::default();
+    progress.levels.record_clear("The Caves", "First", 3);
+    let ctx = RequirementCtx { progress: &progress, level_db: &level_db, treasure_db: &treasure_db };
+    assert!(!ctx.is_level_unlocked(&Area::TheCollapse, 0));
+
+    progress.levels.record_clear("The Caves", "Tool", 1);
+    let ctx = RequirementCtx { progress: &progress, level_db: &level_db, treasure_db: &treasure_db };
+    assert!(ctx.is_level_unlocked(&Area::TheCollapse, 0));
+}
+
+#[test]
+fn clear_level_needs_that_level_cleared() {
+    let result = unlocked_before_and_after(2, |progress| progress.levels.record_clear("The Caves", "First", 1));
+    assert_eq!(result, (false, true));
+}
+
+#[test]
+fn treasure_counts_even_once_sold() {
+    let result = unlocked_before_and_after(3, |progress| {
+        progress.trove.collect(4, "First");
+        assert!(progress.trove.take_one(4));
+    });
+    assert_eq!(result, (false, true));
+}
+
+#[test]
+fn stars_adds_up_the_best_stars_of_every_level() {
+    let result = unlocked_before_and_after(4, |progress| {
+        progress.levels.record_clear("The Caves", "First", 3);
+        progress.levels.record_clear("The Caves", "Previous", 1);
+        progress.levels.record_clear("The Caves", "Previous", 2);
+    });
+    assert_eq!(result, (false, true));
+
+    let not_enough = unlocked_before_and_after(4, |progress| {
+        progress.levels.record_clear("The Caves", "First", 2);
+        progress.levels.record_clear("The Caves", "First", 2);
+    });
+    assert_eq!(not_enough, (false, false));
+}
+
+#[test]
+fn tool_needs_the_tool_unlocked() {
+    let result = unlocked_before_and_after(5, |progress| {
+        progress.tools.unlock(&ToolType::Pickaxe { rotation: PickaxeRotation::Cross })
+    });
+    assert_eq!(result, (false, true));
+}
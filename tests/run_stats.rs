@@ -0,0 +1,113 @@
+mod common;
+
+use miner_thing::{
+    run_stats::{Objective, ObjectiveProgress, ObjectiveState, RunOutcome, RunStats, ToolUse},
+    stability::{LevelStability, Stability},
+    tools::{PickaxeRotation, ToolType},
+};
+
+use common::treasures;
+
+const HAMMER: ToolType = ToolType::TinyHammer;
+const CROSS: ToolType = ToolType::Pickaxe { rotation: PickaxeRotation::Cross };
+
+/// Stability that started at 1000 with `spent` of it used up
+fn stability_after(spent: i32) -> Stability {
+    let mut stability = Stability::new(LevelStability::Normal).with_start(1000);
+    stability.remaining -= spent;
+    stability
+}
+
+fn swung(uses: &[(ToolType, u32)]) -> RunStats {
+    RunStats {
+        tools: uses.iter().map(|(tool, actions)| ToolUse { tool: *tool, actions: *actions }).collect(),
+        ..Default::default()
+    }
+}
+
+fn with_objectives(mut stats: RunStats, objectives: &[Objective]) -> RunStats {
+    stats.objectives = objectives
+        .iter()
+        .map(|objective| ObjectiveProgress { objective: *objective, state: ObjectiveState::OnTrack })
+        .collect();
+    stats
+}
+
+#[test]
+fn stability_objective_breaks_once_more_is_spent() {
+    let stats = RunStats::default();
+    assert!(!Objective::MaxStabilitySpent(200).is_broken(&stats, &stability_after(200)));
+    assert!(Objective::MaxStabilitySpent(200).is_broken(&stats, &stability_after(201)));
+}
+
+#[test]
+fn swing_objective_counts_every_tool() {
+    let stats = swung(&[(HAMMER, 3), (CROSS, 2)]);
+    assert!(!Objective::MaxSwings(5).is_broken(&stats, &stability_after(0)));
+    assert!(Objective::MaxSwings(4).is_broken(&stats, &stability_after(0)));
+}
+
+#[test]
+fn time_objective_breaks_once_over_the_limit() {
+    let stats = RunStats { seconds: 30.5, ..Default::default() };
+    assert!(!Objective::TimeLimit(31.0).is_broken(&stats, &stability_after(0)));
+    assert!(Objective::TimeLimit(30.0).is_broken(&stats, &stability_after(0)));
+}
+
+#[test]
+fn only_tool_objective_compares_the_tool_not_its_name() {
+    let vertical = ToolType::Pickaxe { rotation: PickaxeRotation::Vertical };
+    assert!(!Objective::OnlyTool(CROSS).is_broken(&swung(&[(CROSS, 4)]), &stability_after(0)));
+    assert!(Objective::OnlyTool(CROSS).is_broken(&swung(&[(CROSS, 4), (vertical, 1)]), &stability_after(0)));
+    assert!(!Objective::OnlyTool(HAMMER).is_broken(&RunStats::default(), &stability_after(0)));
+}
+
+#[test]
+fn full_clear_earns_stars_rewards_and_objectives() {
+    let mut stats = with_objectives(
+        swung(&[(HAMMER, 6)]),
+        &[Objective::MaxSwings(10), Objective::MaxSwings(5), Objective::OnlyTool(HAMMER)],
+    );
+    stats.found = vec![1, 2];
+    stats.finish(RunOutcome::Cleared, &stability_after(300), &treasures());
+
+    assert_eq!(stats.outcome, RunOutcome::Cleared);
+    assert_eq!((stats.stability_spent, stats.stability_remaining), (300, 700));
+    assert_eq!(stats.stars, 3);
+    assert_eq!(stats.reward_value, 100);
+    let states = stats.objectives.iter().map(|progress| progress.state).collect::<Vec<_>>();
+    assert_eq!(states, vec![ObjectiveState::Met, ObjectiveState::Failed, ObjectiveState::Met]);
+}
+
+#[test]
+fn objective_failed_while_mining_stays_failed() {
+    let mut stats = with_objectives(swung(&[(HAMMER, 1)]), &[Objective::TimeLimit(60.0)]);
+    stats.objectives[0].state = ObjectiveState::Failed;
+    stats.finish(RunOutcome::Cleared, &stability_after(0), &treasures());
+    assert_eq!(stats.objectives[0].state, ObjectiveState::Failed);
+}
+
+#[test]
+fn clear_with_missed_treasure_earns_no_stars_or_objectives() {
+    let mut stats = with_objectives(swung(&[(HAMMER, 1)]), &[Objective::MaxSwings(10)]);
+    stats.found = vec![1];
+    stats.missed = vec![2];
+    stats.finish(RunOutcome::Cleared, &stability_after(0), &treasures());
+    assert_eq!(stats.stars, 0);
+    assert_eq!(stats.reward_value, 30);
+    assert_eq!(stats.objectives[0].state, ObjectiveState::Failed);
+}
+
+#[test]
+fn leaving_early_keeps_what_was_found_and_a_cave_in_loses_it() {
+    let mut left = swung(&[(HAMMER, 1)]);
+    left.found = vec![2];
+    left.missed = vec![1];
+    left.finish(RunOutcome::LeftEarly, &stability_after(100), &treasures());
+    assert_eq!((left.stars, left.reward_value), (0, 70));
+
+    let mut caved_in = swung(&[(HAMMER, 1)]);
+    caved_in.found = vec![2];
+    caved_in.finish(RunOutcome::Failed, &stability_after(1000), &treasures());
+    assert_eq!((caved_in.stars, caved_in.reward_value, caved_in.stability_remaining), (0, 0, 0));
+}
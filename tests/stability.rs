@@ -0,0 +1,29 @@
+mod common;
+
+use miner_thing::stability::{Stability, StabilityLevel, StabilityThresholds};
+
+use common::levels;
+
+/// Stability for a level of The Collapse, with `spent` out of 1000 used up
+fn stability_in(level_idx: usize, spent: i32) -> Stability {
+    let level_db = levels();
+    let level = &level_db.get("The Collapse").unwrap().levels[level_idx];
+    let mut stability = Stability::new(level.stability).with_thresholds(level.stability_thresholds()).with_start(1000);
+    stability.remaining -= spent;
+    stability
+}
+
+#[test]
+fn levels_without_thresholds_use_their_profile() {
+    assert_eq!(stability_in(0, 0).thresholds, StabilityThresholds { shaky: 0.5, critical: 0.25 });
+    assert_eq!(stability_in(0, 450).level(), StabilityLevel::Stable);
+    assert_eq!(stability_in(1, 450).level(), StabilityLevel::Shaky);
+    assert_eq!(stability_in(1, 700).level(), StabilityLevel::Critical);
+}
+
+#[test]
+fn level_thresholds_override_the_profile() {
+    assert_eq!(stability_in(2, 700).level(), StabilityLevel::Stable);
+    assert_eq!(stability_in(2, 850).level(), StabilityLevel::Shaky);
+    assert_eq!(stability_in(2, 950).level(), StabilityLevel::Critical);
+}
@@ -0,0 +1,101 @@
+use miner_thing::{
+    data_read::parse_data,
+    tools::{PickaxeRotation, ToolRules, ToolType, ToolUnlocks},
+};
+
+const HAMMER: ToolType = ToolType::TinyHammer;
+const HORIZONTAL: ToolType = ToolType::Pickaxe { rotation: PickaxeRotation::Horizontal };
+const VERTICAL: ToolType = ToolType::Pickaxe { rotation: PickaxeRotation::Vertical };
+const CROSS: ToolType = ToolType::Pickaxe { rotation: PickaxeRotation::Cross };
+
+fn parse_rules(data: &str) -> ToolRules {
+    parse_data(data).unwrap()
+}
+
+/// Starts with the tiny hammer and horizontal pickaxe unlocked
+fn unlocks_with(tools: &[ToolType]) -> ToolUnlocks {
+    let mut unlocks = ToolUnlocks::default();
+    for tool in tools {
+        unlocks.unlock(tool);
+    }
+    unlocks
+}
+
+#[test]
+fn default_rules_bring_every_unlocked_tool() {
+    let loadout = ToolRules::default().pick_loadout(&unlocks_with(&[CROSS]), []);
+    assert_eq!(loadout.size(), None);
+    assert_eq!(loadout.available(), [HAMMER, HORIZONTAL, CROSS]);
+    assert_eq!(loadout.picked(), [HAMMER, HORIZONTAL, CROSS]);
+}
+
+#[test]
+fn available_keeps_to_the_allowed_tools_and_lends_required_ones() {
+    let rules = parse_rules("{ allowed: [{ Pickaxe: { rotation: 'Horizontal' } }], required: ['TinyHammer'] }");
+    assert_eq!(rules.available(&unlocks_with(&[VERTICAL, CROSS])), [HAMMER, HORIZONTAL]);
+
+    let lent = parse_rules("{ required: [{ Pickaxe: { rotation: 'Cross' } }] }");
+    assert_eq!(lent.available(&ToolUnlocks::default()), [HAMMER, HORIZONTAL, CROSS]);
+}
+
+#[test]
+fn available_lends_the_allowed_tools_when_none_are_unlocked() {
+    let rules = parse_rules("{ allowed: [{ Pickaxe: { rotation: 'Vertical' } }, { Pickaxe: { rotation: 'Cross' } }] }");
+    let available = rules.available(&ToolUnlocks::default());
+    assert_eq!(available, [VERTICAL, CROSS]);
+
+    let loadout = rules.pick_loadout(&ToolUnlocks::default(), [HAMMER]);
+    assert_eq!(loadout.picked(), [VERTICAL, CROSS]);
+}
+
+#[test]
+fn loadout_starts_with_required_then_preferred_tools_up_to_the_size() {
+    let rules = parse_rules("{ required: [{ Pickaxe: { rotation: 'Vertical' } }], loadout_size: 2 }");
+    let loadout = rules.pick_loadout(&unlocks_with(&[CROSS]), [CROSS, HAMMER]);
+    assert_eq!(loadout.size(), Some(2));
+    assert_eq!(loadout.picked(), [VERTICAL, CROSS]);
+    assert!(loadout.is_required(&VERTICAL));
+}
+
+#[test]
+fn loadout_skips_preferred_tools_that_are_not_available() {
+    let rules = parse_rules("{ allowed: ['TinyHammer', { Pickaxe: { rotation: 'Horizontal' } }], loadout_size: 1 }");
+    let loadout = rules.pick_loadout(&unlocks_with(&[CROSS]), [CROSS, HORIZONTAL]);
+    assert_eq!(loadout.picked(), [HORIZONTAL]);
+}
+
+#[test]
+fn loadout_size_makes_room_for_every_required_tool_and_at_least_one() {
+    let rules = parse_rules("{ required: ['TinyHammer', { Pickaxe: { rotation: 'Cross' } }], loadout_size: 1 }");
+    assert_eq!(rules.loadout_size(), Some(2));
+    assert_eq!(parse_rules("{ loadout_size: 0 }").loadout_size(), Some(1));
+
+    let loadout = parse_rules("{ loadout_size: 0 }").pick_loadout(&ToolUnlocks::default(), []);
+    assert_eq!(loadout.picked(), [HAMMER]);
+}
+
+#[test]
+fn toggle_keeps_required_and_last_tools_and_respects_the_size() {
+    let rules = parse_rules("{ required: ['TinyHammer'], loadout_size: 2 }");
+    let mut loadout = rules.pick_loadout(&unlocks_with(&[VERTICAL]), [HAMMER]);
+    assert_eq!(loadout.picked(), [HAMMER, HORIZONTAL]);
+
+    loadout.toggle(HAMMER);
+    loadout.toggle(VERTICAL);
+    assert_eq!(loadout.picked(), [HAMMER, HORIZONTAL]);
+
+    loadout.toggle(HORIZONTAL);
+    loadout.toggle(VERTICAL);
+    assert_eq!(loadout.picked(), [HAMMER, VERTICAL]);
+}
+
+#[test]
+fn tool_made_required_is_brought_even_when_locked_or_over_the_size() {
+    let rules = parse_rules("{ allowed: ['TinyHammer'], loadout_size: 1 }").with_required(CROSS);
+    let loadout = rules.pick_loadout(&ToolUnlocks::default(), [HAMMER]);
+    assert_eq!(loadout.picked(), [CROSS]);
+    assert!(loadout.is_required(&CROSS));
+
+    let loadout = ToolRules::default().with_required(CROSS).pick_loadout(&ToolUnlocks::default(), [CROSS]);
+    assert_eq!(loadout.picked(), [CROSS, HAMMER, HORIZONTAL]);
+}
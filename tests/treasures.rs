@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use miner_thing::{
+    data_read::{parse_data, DropEntry, PityRule, Rarity, TreasureDb, TreasureInfo},
+    point::{Grid, Rotation},
+    treasures::build_drop_pool,
+};
+use rand::{distributions::WeightedIndex, prelude::Distribution, rngs::StdRng, SeedableRng};
+
+/// One single tile treasure of each rarity, the id being the rarity's position in the list
+fn one_of_each_rarity() -> TreasureDb {
+    parse_data(
+        "[
+            { id: 0, name: 'Common', shape: [0], width: 1, height: 1, rarity: 'Common' },
+            { id: 1, name: 'Uncommon', shape: [1], width: 1, height: 1, rarity: 'Uncommon' },
+            { id: 2, name: 'Rare', shape: [2], width: 1, height: 1, rarity: 'Rare' },
+            { id: 3, name: 'Legendary', shape: [3], width: 1, height: 1, rarity: 'Legendary' },
+        ]",
+    )
+    .unwrap()
+}
+
+/// How often each rarity is picked from the pool over `draws` seeded draws
+fn sample_rarities(tdb: &TreasureDb, drop_table: Option<&[DropEntry]>, draws: u32) -> HashMap<Rarity, u32> {
+    let pool = build_drop_pool(tdb, drop_table, None);
+    let weights = WeightedIndex::new(pool.iter().map(|(_, weight, _)| *weight)).unwrap();
+    let mut rng = StdRng::seed_from_u64(0x5eed);
+    let mut counts = HashMap::new();
+    for _ in 0..draws {
+        *counts.entry(pool[weights.sample(&mut rng)].0.rarity).or_default() += 1;
+    }
+    counts
+}
+
+fn bone(orientations: &str) -> TreasureInfo {
+    parse_data(&format!(
+        "{{ id: 2, name: 'Bone', shape: [5, 6, -1, -1, -1, 7], width: 3, height: 2, orientations: [{orientations}] }}"
+    ))
+    .unwrap()
+}
+
+#[test]
+fn orientation_without_atlas_turns_the_authored_sprites() {
+    let treasure = bone("{ rotation: 'Cw90' }");
+    let shapes = treasure.placeable_shapes();
+    assert_eq!(shapes.len(), 2);
+    assert_eq!(shapes[0].sprite_rotation, Rotation::None);
+    assert_eq!(shapes[1].grid, treasure.shape_grid().unwrap().rotated(Rotation::Cw90));
+    assert_eq!(shapes[1].sprite_rotation, Rotation::Cw90);
+    assert!(!shapes[1].sprite_mirrored);
+}
+
+#[test]
+fn orientation_with_atlas_uses_its_own_sprites() {
+    let treasure = bone("{ mirrored: true, atlas: [1, 2, 0, 0, 0, 3] }");
+    let shape = &treasure.placeable_shapes()[1];
+    // authored top row first, the grid has the bottom row first
+    assert_eq!(shape.grid, Grid::from_vec(3, 2, vec![3, -1, -1, -1, 2, 1]).unwrap());
+    assert_eq!(shape.sprite_rotation, Rotation::None);
+    assert!(!shape.sprite_mirrored);
+}
+
+#[test]
+fn orientation_with_mismatched_atlas_is_skipped() {
+    assert_eq!(bone("{ rotation: 'Cw180', atlas: [1, 2] }").placeable_shapes().len(), 1);
+}
+
+#[test]
+fn pool_without_drop_table_follows_rarity_weights() {
+    let draws = 20_000;
+    let counts = sample_rarities(&one_of_each_rarity(), None, draws);
+    for rarity in [Rarity::Common, Rarity::Uncommon, Rarity::Rare, Rarity::Legendary] {
+        let share = counts.get(&rarity).copied().unwrap_or(0) as f32 / draws as f32;
+        let expected = rarity.default_weight() as f32 / 100.0;
+        assert!((share - expected).abs() < 0.02, "{rarity:?} was picked {share} of the time, expected {expected}");
+    }
+}
+
+#[test]
+fn pool_uses_drop_table_weights_and_skips_missing_treasures() {
+    let table = [
+        DropEntry { treasure: 0, weight: 3 },
+        DropEntry { treasure: 3, weight: 1 },
+        DropEntry { treasure: 2, weight: 0 },
+        DropEntry { treasure: 99, weight: 5 },
+    ];
+    let tdb = one_of_each_rarity();
+    let pool = build_drop_pool(&tdb, Some(&table), None);
+    let picked = pool.iter().map(|(t, weight, _)| (t.id, *weight)).collect::<Vec<_>>();
+    assert_eq!(picked, vec![(0, 3), (3, 1)]);
+
+    let counts = sample_rarities(&tdb, Some(&table), 20_000);
+    let legendary_share = counts[&Rarity::Legendary] as f32 / 20_000.0;
+    assert!((legendary_share - 0.25).abs() < 0.02, "legendary was picked {legendary_share} of the time");
+}
+
+#[test]
+fn pool_with_min_rarity_only_has_that_rarity_or_better() {
+    let tdb = one_of_each_rarity();
+    let pool = build_drop_pool(&tdb, None, Some(Rarity::Rare));
+    assert!(!pool.is_empty());
+    assert!(pool.iter().all(|(t, _, _)| t.rarity >= Rarity::Rare));
+}
+
+#[test]
+fn pity_kicks_in_on_the_last_clear_of_the_window() {
+    let rule = PityRule { rarity: Rarity::Rare, within: 3 };
+    let mut dry_streak = 0;
+    let mut due = vec![];
+    for _ in 0..3 {
+        due.push(rule.is_due(dry_streak));
+        dry_streak = rule.next_streak(dry_streak, &[Rarity::Common, Rarity::Uncommon]);
+    }
+    assert_eq!(due, vec![false, false, true]);
+    assert_eq!(dry_streak, 3);
+}
+
+#[test]
+fn pity_resets_once_the_rarity_or_better_is_found() {
+    let rule = PityRule { rarity: Rarity::Rare, within: 3 };
+    assert_eq!(rule.next_streak(2, &[Rarity::Common, Rarity::Legendary]), 0);
+    assert_eq!(rule.next_streak(2, &[Rarity::Rare]), 0);
+    assert_eq!(rule.next_streak(2, &[]), 3);
+    assert!(!rule.is_due(0));
+}